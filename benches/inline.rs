@@ -0,0 +1,39 @@
+//! Tight `incr`/`get` loops, inlined versus forced out-of-line.
+//!
+//! The single-word backing's methods are `#[inline]` so these loops compile
+//! down to the bare atomic instructions in the caller's crate.
+//! Run with `cargo bench --bench inline`.
+
+extern crate counter64;
+#[macro_use]
+extern crate criterion;
+
+use std::hint::black_box;
+
+use counter64::Counter;
+use criterion::Criterion;
+
+#[inline(never)]
+fn incr_outlined(counter: &Counter) -> u64 {
+    counter.incr()
+}
+
+#[inline(never)]
+fn get_outlined(counter: &Counter) -> u64 {
+    counter.get()
+}
+
+fn inline(c: &mut Criterion) {
+    let counter = black_box(Counter::new());
+    let mut group = c.benchmark_group("inline");
+
+    group.bench_function("incr inlined", |b| b.iter(|| counter.incr()));
+    group.bench_function("incr outlined", |b| b.iter(|| incr_outlined(&counter)));
+    group.bench_function("get inlined", |b| b.iter(|| counter.get()));
+    group.bench_function("get outlined", |b| b.iter(|| get_outlined(&counter)));
+
+    group.finish();
+}
+
+criterion_group!(benches, inline);
+criterion_main!(benches);
@@ -0,0 +1,76 @@
+//! Latency of the first increment after promotion, with and without `prewarm`.
+//!
+//! Each round builds a fresh counter and evicts it from the cache by
+//! sweeping a large buffer, like a counter which sat idle, before the measured increment.
+//! Another counter is incremented right before it, so the code path is equally warm.
+//! `prewarm` is only a cache touch, so between it and the measured increment
+//! some other work sweeps a part of the buffer, from none at all up to the whole of it.
+//! Run with `cargo bench --bench prewarm --features adaptive`.
+
+extern crate counter64;
+#[macro_use]
+extern crate criterion;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use counter64::AdaptiveCounter;
+use criterion::Criterion;
+
+const SHARDS: usize = 64;
+const EVICT_BYTES: usize = 32 << 20;
+/// Bytes swept between `prewarm` and the first increment
+const GAPS: [usize; 4] = [0, 32 << 10, 1 << 20, EVICT_BYTES];
+
+fn evict(buf: &mut [u8]) {
+    for i in (0..buf.len()).step_by(64) {
+        buf[i] = buf[i].wrapping_add(1);
+    }
+    black_box(&buf);
+}
+
+fn first_incr(buf: &mut [u8], rounds: u64, prewarm: bool, gap: usize) -> Duration {
+    let mut total = Duration::ZERO;
+    let other = AdaptiveCounter::with_shards(SHARDS).unwrap();
+    other.promote();
+
+    for _ in 0..rounds {
+        let counter = AdaptiveCounter::with_shards(SHARDS).unwrap();
+        counter.promote();
+        evict(buf);
+
+        if prewarm {
+            counter.prewarm();
+        }
+        evict(&mut buf[..gap]);
+        // Warm the code path, so only the counter's own cache lines differ
+        black_box(&other).incr();
+
+        let start = Instant::now();
+        black_box(&counter).incr();
+        total += start.elapsed();
+    }
+
+    total
+}
+
+fn prewarm(c: &mut Criterion) {
+    let mut buf = vec![0u8; EVICT_BYTES];
+    let mut group = c.benchmark_group("prewarm");
+    // Every round sweeps the whole buffer
+    group.sample_size(10);
+
+    group.bench_function("cold first incr", |b| {
+        b.iter_custom(|rounds| first_incr(&mut buf, rounds, false, 0))
+    });
+    for &gap in &GAPS {
+        group.bench_function(format!("prewarmed, {}KiB gap", gap >> 10), |b| {
+            b.iter_custom(|rounds| first_incr(&mut buf, rounds, true, gap))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, prewarm);
+criterion_main!(benches);
@@ -0,0 +1,153 @@
+//! Throughput and latency of every backing against a `Mutex<u64>` baseline.
+//!
+//! The multi-word backings are forced by naming them, so this runs on any host.
+//! Run with `cargo bench --bench strategies`,
+//! add `--features portable-atomic` to include `Portable64`
+//! and `--features spin` to include `Locked64`.
+//! The conclusions are written up in the `counter64::backing` module docs.
+
+extern crate counter64;
+#[macro_use]
+extern crate criterion;
+
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use counter64::backing::{self, Backing};
+use counter64::Counter64;
+use criterion::Criterion;
+
+const WRITERS: u64 = 3;
+
+/// Way to keep a shared 64bit count
+trait Strategy: Default + Sync {
+    fn incr(&self);
+    fn get(&self) -> u64;
+}
+
+impl<B: Backing> Strategy for Counter64<B> {
+    fn incr(&self) {
+        Counter64::incr(self);
+    }
+
+    fn get(&self) -> u64 {
+        Counter64::get(self)
+    }
+}
+
+/// The obvious alternative to the sequence lock
+#[derive(Default)]
+struct MutexU64(Mutex<u64>);
+
+impl Strategy for MutexU64 {
+    fn incr(&self) {
+        *self.0.lock().unwrap() += 1;
+    }
+
+    fn get(&self) -> u64 {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// `iters` reads while `WRITERS` threads keep incrementing
+fn contended_get<S: Strategy>(iters: u64) -> Duration {
+    let counter = S::default();
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|s| {
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut ops = 0u64;
+                    while !stop.load(Ordering::Relaxed) {
+                        counter.incr();
+                        ops += 1;
+                    }
+                    ops
+                })
+            })
+            .collect();
+
+        let start = Instant::now();
+        for _ in 0..iters {
+            black_box(counter.get());
+        }
+        let elapsed = start.elapsed();
+        stop.store(true, Ordering::Relaxed);
+
+        let ops: u64 = writers.into_iter().map(|w| w.join().unwrap()).sum();
+        assert_eq!(counter.get(), ops);
+        elapsed
+    })
+}
+
+/// `iters` increments split over `WRITERS` threads while one thread keeps reading
+fn contended_incr<S: Strategy>(iters: u64) -> Duration {
+    let counter = S::default();
+    let stop = AtomicBool::new(false);
+    let per_writer = iters / WRITERS + 1;
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            while !stop.load(Ordering::Relaxed) {
+                black_box(counter.get());
+            }
+        });
+
+        let start = Instant::now();
+        thread::scope(|s| {
+            for _ in 0..WRITERS {
+                s.spawn(|| {
+                    for _ in 0..per_writer {
+                        counter.incr();
+                    }
+                });
+            }
+        });
+        let elapsed = start.elapsed();
+        stop.store(true, Ordering::Relaxed);
+
+        assert_eq!(counter.get(), per_writer * WRITERS);
+        elapsed
+    })
+}
+
+fn bench<S: Strategy>(c: &mut Criterion, name: &str) {
+    let mut group = c.benchmark_group(name);
+
+    group.bench_function("incr", |b| {
+        let counter = S::default();
+        b.iter(|| black_box(&counter).incr())
+    });
+    group.bench_function("get", |b| {
+        let counter = S::default();
+        b.iter(|| black_box(&counter).get())
+    });
+    group.bench_function(format!("incr, {} writers 1 reader", WRITERS), |b| {
+        b.iter_custom(contended_incr::<S>)
+    });
+    group.bench_function(format!("get, {} writers", WRITERS), |b| {
+        b.iter_custom(contended_get::<S>)
+    });
+
+    group.finish();
+}
+
+fn strategies(c: &mut Criterion) {
+    #[cfg(target_has_atomic = "64")]
+    bench::<Counter64<backing::Atomic64>>(c, "Atomic64");
+    #[cfg(feature = "portable-atomic")]
+    bench::<Counter64<backing::Portable64>>(c, "Portable64");
+    #[cfg(target_has_atomic = "32")]
+    bench::<Counter64<backing::Lanes32>>(c, "Lanes32");
+    bench::<Counter64<backing::Lanes16>>(c, "Lanes16");
+    #[cfg(feature = "spin")]
+    bench::<Counter64<backing::Locked64>>(c, "Locked64");
+    bench::<MutexU64>(c, "Mutex<u64>");
+}
+
+criterion_group!(benches, strategies);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+//! `add` versus `fetch_add_relaxed_unchecked` in a tight loop.
+//!
+//! In release builds `add` has no overflow check either, so both should
+//! compile to the same `lock xadd` and any difference is the returned value.
+//! Run with `cargo bench --bench unchecked`.
+
+extern crate counter64;
+#[macro_use]
+extern crate criterion;
+
+use std::hint::black_box;
+
+use counter64::Counter;
+use criterion::Criterion;
+
+fn unchecked(c: &mut Criterion) {
+    let counter = black_box(Counter::new());
+    let mut group = c.benchmark_group("unchecked");
+
+    group.bench_function("add", |b| b.iter(|| counter.add(black_box(1))));
+    group.bench_function("add unchecked", |b| {
+        b.iter(|| counter.fetch_add_relaxed_unchecked(black_box(1)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, unchecked);
+criterion_main!(benches);
@@ -0,0 +1,72 @@
+//! Clamped increments on the weak CAS versus a strong CAS loop.
+//!
+//! The difference shows on LL/SC targets like ARM, where a strong CAS is
+//! a retry loop itself. On x86 both compile to `lock cmpxchg`, so run it
+//! there only as a proxy. Run with `cargo bench --bench weak_cas`.
+
+extern crate counter64;
+#[macro_use]
+extern crate criterion;
+
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use counter64::Counter;
+use criterion::Criterion;
+
+const THREADS: u64 = 4;
+
+fn clamped_strong(counter: &Counter, cap: u64) {
+    let mut current = counter.get();
+
+    while current < cap {
+        match counter.compare_exchange(current, current + 1) {
+            Ok(_) => return,
+            Err(actual) => current = actual,
+        }
+    }
+}
+
+fn clamped_weak(counter: &Counter, cap: u64) {
+    let _ = counter.fetch_update_weak(|num| if num < cap { Some(num + 1) } else { None });
+}
+
+/// Runs `iters` clamped increments split over `threads` threads
+fn run(threads: u64, iters: u64, f: fn(&Counter, u64)) -> Duration {
+    let counter = Counter::new();
+    let per_thread = iters / threads;
+
+    let start = Instant::now();
+    thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                for _ in 0..per_thread {
+                    f(black_box(&counter), u64::MAX);
+                }
+            });
+        }
+    });
+    let elapsed = start.elapsed();
+
+    assert_eq!(counter.get(), per_thread * threads);
+    elapsed
+}
+
+fn weak_cas(c: &mut Criterion) {
+    let mut group = c.benchmark_group("weak_cas");
+
+    for &threads in &[1, THREADS] {
+        group.bench_function(format!("strong, {} threads", threads), |b| {
+            b.iter_custom(|iters| run(threads, iters, clamped_strong))
+        });
+        group.bench_function(format!("weak, {} threads", threads), |b| {
+            b.iter_custom(|iters| run(threads, iters, clamped_weak))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, weak_cas);
+criterion_main!(benches);
@@ -0,0 +1,20 @@
+//! Count past the `u32` range, on any target.
+//!
+//! On wasm: `cargo run --example demo --target wasm32-wasip1`
+//! with a wasm runtime configured as the runner.
+
+extern crate counter64;
+
+use std::any::type_name;
+
+use counter64::{backing, Counter};
+
+fn main() {
+    let counter = unsafe { Counter::with_init(u32::MAX as u64 - 2) };
+
+    for _ in 0..5 {
+        println!("{}", counter.incr());
+    }
+
+    println!("{} with {}", counter.get(), type_name::<backing::Native>());
+}
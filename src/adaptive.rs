@@ -0,0 +1,238 @@
+//! Counter which shards itself once it's contended.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+use error::CounterError;
+use Counter;
+
+/// CAS failures on the single atomic before the counter promotes itself
+const PROMOTE_AFTER: u64 = 1024;
+
+/// Counter on its own cache line, so shards don't false-share
+#[derive(Debug, Default)]
+#[repr(align(64))]
+struct Shard(Counter);
+
+/// Counter which starts as a single atomic and shards itself under contention
+///
+/// Every increment first tries a CAS on the single atomic. Once enough of them
+/// fail, it promotes itself for good and increments go to per-thread shards,
+/// which scale with the number of threads instead of bouncing one cache line.
+///
+/// # Consistency
+///
+/// Before promotion [`get`](#method.get) is an exact snapshot like `Counter::get`.
+/// After promotion it sums the shards one by one, so like
+/// [`snapshot_all`](fn.snapshot_all.html) the total may never have been
+/// the value at any single instant. It's still never below the increments
+/// completed before the call, and never goes backward across calls.
+/// Increments don't return the previous value, as there's no single one after promotion.
+#[derive(Debug)]
+pub struct AdaptiveCounter {
+    single: Counter,
+    contention: Counter,
+    promoted: AtomicBool,
+    shards: Box<[Shard]>,
+}
+
+impl Default for AdaptiveCounter {
+    fn default() -> Self {
+        AdaptiveCounter::new()
+    }
+}
+
+impl AdaptiveCounter {
+    /// Create new adaptive counter from 0, with a shard per available CPU
+    pub fn new() -> Self {
+        let shards = thread::available_parallelism().map_or(1, |n| n.get());
+        AdaptiveCounter::with_shards(shards).unwrap()
+    }
+
+    /// Create new adaptive counter from 0, with `shards` shards for after promotion
+    ///
+    /// Returns `Err(CounterError::InvalidShardCount)` if `shards` is 0.
+    pub fn with_shards(shards: usize) -> Result<Self, CounterError> {
+        if shards == 0 {
+            return Err(CounterError::InvalidShardCount);
+        }
+
+        Ok(AdaptiveCounter {
+            single: Counter::new(),
+            contention: Counter::new(),
+            promoted: AtomicBool::new(false),
+            shards: (0..shards).map(|_| Shard::default()).collect(),
+        })
+    }
+
+    /// Get counter's current value
+    ///
+    /// See the [consistency](#consistency) notes for after the promotion.
+    pub fn get(&self) -> u64 {
+        let single = self.single.get();
+
+        if !self.is_promoted() {
+            return single;
+        }
+
+        self.shards.iter().fold(single, |sum, shard| sum.wrapping_add(shard.0.get()))
+    }
+
+    /// Whether the counter has promoted itself to the shards
+    pub fn is_promoted(&self) -> bool {
+        self.promoted.load(Ordering::Acquire)
+    }
+
+    /// Promote the counter to the shards now, for counters known to be hot
+    pub fn promote(&self) {
+        self.promoted.store(true, Ordering::Release);
+    }
+
+    /// Touch every shard once, so the first increments after promotion
+    /// may find them in the cache
+    ///
+    /// Each shard is written with a zero add, which brings its cache line
+    /// in for writing. It doesn't change the value nor promote the counter.
+    ///
+    /// It's only a cache touch. The shards are allocated and zeroed by the constructor,
+    /// so there are no page faults left to avoid, and the lines stay cached only until
+    /// other work evicts them. Call it right before a burst of increments, not at startup.
+    /// `benches/prewarm.rs` measured the first increment right after it a few tens of ns
+    /// faster than a cold one, about the noise, and no faster after a gap touching 32KiB.
+    pub fn prewarm(&self) {
+        for shard in self.shards.iter() {
+            shard.0.wrapping_add(0);
+        }
+    }
+
+    /// Increase counter by `n`
+    ///
+    /// Wraps around on overflow.
+    pub fn add(&self, n: u64) {
+        if !self.is_promoted() {
+            let mut current = self.single.get();
+
+            loop {
+                match self.single.compare_exchange(current, current.wrapping_add(n)) {
+                    Ok(_) => return,
+                    Err(actual) => current = actual,
+                }
+
+                if self.record_contention() {
+                    break;
+                }
+            }
+        }
+
+        self.shards[shard_index() % self.shards.len()].0.wrapping_add(n);
+    }
+
+    /// Count a CAS failure, and return whether it promoted the counter
+    fn record_contention(&self) -> bool {
+        if self.contention.incr_return_new() < PROMOTE_AFTER {
+            return false;
+        }
+
+        self.promote();
+        true
+    }
+
+    /// Increase counter by 1
+    pub fn incr(&self) {
+        self.add(1)
+    }
+}
+
+/// Index of the current thread, assigned round-robin on first use
+fn shard_index() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    thread_local! {
+        static INDEX: usize = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+
+    INDEX.with(|&index| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hammer(counter: &AdaptiveCounter, threads: u64, iters: u64) {
+        thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| {
+                    for _ in 0..iters {
+                        counter.incr();
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_correct_before_and_after_promotion() {
+        let counter = AdaptiveCounter::with_shards(4).unwrap();
+
+        hammer(&counter, 1, 10000);
+        assert!(!counter.is_promoted());
+        assert_eq!(counter.get(), 10000);
+
+        counter.promote();
+        assert_eq!(counter.get(), 10000);
+
+        hammer(&counter, 8, 50000);
+        assert_eq!(counter.get(), 410000);
+    }
+
+    #[test]
+    fn test_multithread_hammer() {
+        let counter = AdaptiveCounter::new();
+
+        hammer(&counter, 16, 50000);
+        assert_eq!(counter.get(), 800000);
+
+        counter.add(u64::MAX);
+        assert_eq!(counter.get(), 799999);
+    }
+
+    #[test]
+    fn test_promotes_under_contention() {
+        let counter = AdaptiveCounter::with_shards(2).unwrap();
+        counter.add(100);
+
+        for _ in 1..PROMOTE_AFTER {
+            assert!(!counter.record_contention());
+        }
+        assert!(!counter.is_promoted());
+
+        assert!(counter.record_contention());
+        assert!(counter.is_promoted());
+
+        counter.add(5);
+        assert_eq!(counter.single.get(), 100);
+        assert_eq!(counter.get(), 105);
+    }
+
+    #[test]
+    fn test_prewarm_keeps_value() {
+        let counter = AdaptiveCounter::with_shards(8).unwrap();
+        counter.add(42);
+
+        counter.prewarm();
+        assert!(!counter.is_promoted());
+        assert_eq!(counter.get(), 42);
+
+        counter.promote();
+        counter.prewarm();
+        assert_eq!(counter.get(), 42);
+    }
+
+    #[test]
+    fn test_zero_shards() {
+        assert_eq!(
+            AdaptiveCounter::with_shards(0).unwrap_err(),
+            CounterError::InvalidShardCount,
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Fixed pool of counters, usable in a `static` without allocation.
+
+use Counter;
+use COUNTER_INIT;
+
+/// `N` independent counters indexed from 0
+///
+/// `const`-constructible and `no_std`, e.g. for a `static` pool of counters
+/// on embedded targets without an allocator.
+#[derive(Debug)]
+pub struct CounterArray<const N: usize> {
+    counters: [Counter; N],
+}
+
+impl<const N: usize> Default for CounterArray<N> {
+    fn default() -> Self {
+        CounterArray::new()
+    }
+}
+
+impl<const N: usize> CounterArray<N> {
+    /// Create new array with every counter from 0
+    pub const fn new() -> Self {
+        CounterArray {
+            counters: [COUNTER_INIT; N],
+        }
+    }
+
+    /// Increase counter `i` by 1, and return its previous value
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= N`.
+    pub fn incr(&self, i: usize) -> u64 {
+        self.counters[i].incr()
+    }
+
+    /// Get counter `i`'s current value
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= N`.
+    pub fn get(&self, i: usize) -> u64 {
+        self.counters[i].get()
+    }
+
+    /// Iterate over every counter's current value, in order
+    ///
+    /// Each value is read as the iterator reaches it,
+    /// so they're not consistent across the counters.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = u64> + '_ {
+        self.counters.iter().map(Counter::get)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_static_array() {
+        static COUNTERS: CounterArray<16> = CounterArray::new();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                thread::spawn(|| {
+                    for i in 0..1000 {
+                        COUNTERS.incr(i % 3 * 5);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(COUNTERS.get(0), 1336);
+        assert_eq!(COUNTERS.get(5), 1332);
+        assert_eq!(COUNTERS.get(10), 1332);
+        assert_eq!(COUNTERS.iter().len(), 16);
+        assert_eq!(COUNTERS.iter().sum::<u64>(), 4000);
+        assert_eq!(COUNTERS.iter().filter(|&v| v == 0).count(), 13);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_range() {
+        CounterArray::<4>::new().get(4);
+    }
+}
@@ -0,0 +1,755 @@
+//! Storage strategies of the `Counter64`.
+//!
+//! [`Native`](type.Native.html) is selected for the target and used by the [`Counter`](../type.Counter.html).
+//! Others can be picked explicitly, e.g. `Counter64<Lanes32>`,
+//! to exercise the multi-word fallbacks on any host.
+//!
+//! # Choosing the backing
+//!
+//! `Native` prefers a lock-free 64bit atomic, then `Portable64` when the
+//! `portable-atomic` feature is on, and only then the lanes. `benches/strategies.rs`
+//! on x86_64, with the multi-word backings forced:
+//!
+//! - `Atomic64` and `Portable64` are the same instructions, about 12ns per `incr`
+//!   and under 1ns per `get`, and stay there with 3 writers and a reader.
+//! - The lanes match them on uncontended `incr` but `get` is 5-7x slower,
+//!   and under 3 writers both sides slow down: `incr` to about 30ns and
+//!   `get` to hundreds of ns as the reader retries.
+//! - `Mutex<u64>` is twice as slow uncontended and no better than the lanes contended,
+//!   so the lanes stay the fallback over a lock.
+//! - `Locked64` is as fast as `Atomic64` uncontended but collapses when a lock holder
+//!   is preempted, hence it's never selected and has to be named.
+
+use core::fmt::Debug;
+use core::mem;
+use core::sync::atomic::{fence, Ordering};
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64;
+#[cfg(target_has_atomic = "32")]
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::AtomicU16;
+
+#[cfg(feature = "portable-atomic")]
+use portable_atomic::AtomicU64 as PortableAtomicU64;
+#[cfg(feature = "spin")]
+use spin::Mutex as SpinMutex;
+
+use seqlock::SeqLock;
+pub use seqlock::READ_RETRY_LIMIT;
+
+/// Ordering of the counting operations
+///
+/// `Relaxed` by default, as counting alone doesn't synchronize anything.
+/// The `seqcst` feature flips it to `SeqCst` for maximum conservatism,
+/// e.g. while hunting a suspected ordering bug, at the cost of full fences
+/// on weakly ordered targets. Lanes are still ordered by the sequence lock.
+#[cfg(not(feature = "seqcst"))]
+pub(crate) const ORDER: Ordering = Ordering::Relaxed;
+
+/// Ordering of the counting operations
+#[cfg(feature = "seqcst")]
+pub(crate) const ORDER: Ordering = Ordering::SeqCst;
+
+/// Ordering of the reads which synchronize with [`RELEASE`](constant.RELEASE.html)
+#[cfg(not(feature = "seqcst"))]
+const ACQUIRE: Ordering = Ordering::Acquire;
+
+/// Ordering of the writes which publish the data written before them
+#[cfg(not(feature = "seqcst"))]
+pub(crate) const RELEASE: Ordering = Ordering::Release;
+
+#[cfg(feature = "seqcst")]
+const ACQUIRE: Ordering = Ordering::SeqCst;
+
+#[cfg(feature = "seqcst")]
+pub(crate) const RELEASE: Ordering = Ordering::SeqCst;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Storage of the `Counter64`
+///
+/// This trait is sealed, its methods are the primitives
+/// every other operation of the `Counter64` is built on.
+pub trait Backing: sealed::Sealed + Debug + Sized + Send + Sync + 'static {
+    #[doc(hidden)]
+    const INIT: Self;
+
+    #[doc(hidden)]
+    fn with_value(num: u64) -> Self;
+
+    #[doc(hidden)]
+    fn load(&self) -> u64;
+
+    #[doc(hidden)]
+    fn load_approx(&self) -> u64 {
+        self.load()
+    }
+
+    /// Lanes are read under the sequence lock, which already acquires
+    #[doc(hidden)]
+    fn load_acquire(&self) -> u64 {
+        self.load()
+    }
+
+    #[doc(hidden)]
+    fn try_load(&self) -> Option<u64> {
+        Some(self.load())
+    }
+
+    /// Run `f` in the middle of a write, to test the reads which overlap one
+    ///
+    /// Returns `None` without running `f` if writes are single instructions.
+    #[cfg(test)]
+    #[doc(hidden)]
+    fn with_write_held<T, F: FnOnce() -> T>(&self, f: F) -> Option<T> {
+        let _ = f;
+        None
+    }
+
+    #[doc(hidden)]
+    fn load_within(&self, _max_retries: u32) -> Result<u64, u64> {
+        Ok(self.load())
+    }
+
+    /// Single-word backings never retry reads
+    #[cfg(feature = "diagnostics")]
+    #[doc(hidden)]
+    fn load_with_retries(&self) -> (u64, u32) {
+        (self.load(), 0)
+    }
+
+    /// Single-word backings have no sequence lock, the value stands in
+    /// for the generation
+    #[doc(hidden)]
+    fn load_with_generation(&self) -> (u64, u64) {
+        let num = self.load();
+        (num, num)
+    }
+
+    #[doc(hidden)]
+    fn fetch_add(&self, n: u64) -> u64;
+
+    /// Lanes are written under the sequence lock, which already acquires and releases.
+    /// `SeqCst` additionally fences around it.
+    #[doc(hidden)]
+    fn fetch_add_ordered(&self, n: u64, order: Ordering) -> u64 {
+        if order == Ordering::SeqCst {
+            fence(Ordering::SeqCst);
+        }
+
+        let prev = self.fetch_add(n);
+
+        if order == Ordering::SeqCst {
+            fence(Ordering::SeqCst);
+        }
+
+        prev
+    }
+
+    #[doc(hidden)]
+    fn swap(&self, value: u64) -> u64;
+
+    #[doc(hidden)]
+    fn fetch_update<F>(&self, f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>;
+
+    /// Lanes only retry when another writer got in between, so it's the same as `fetch_update`
+    #[doc(hidden)]
+    fn fetch_update_weak<F>(&self, f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>
+    {
+        self.fetch_update(f)
+    }
+
+    #[doc(hidden)]
+    fn compare_exchange(&self, current: u64, new: u64) -> Result<u64, u64> {
+        self.fetch_update(|num| if num == current { Some(new) } else { None })
+    }
+
+    #[doc(hidden)]
+    fn fetch_max(&self, value: u64) -> u64 {
+        self.fetch_update(|num| if value > num { Some(value) } else { None })
+            .unwrap_or_else(|num| num)
+    }
+
+    #[doc(hidden)]
+    fn fetch_min(&self, value: u64) -> u64 {
+        self.fetch_update(|num| if value < num { Some(value) } else { None })
+            .unwrap_or_else(|num| num)
+    }
+}
+
+/// Backing selected for the target
+///
+/// See [choosing the backing](index.html#choosing-the-backing) for the measurements behind the order.
+#[cfg(target_has_atomic = "64")]
+pub type Native = Atomic64;
+
+/// Backing selected for the target
+///
+/// See [choosing the backing](index.html#choosing-the-backing) for the measurements behind the order.
+#[cfg(all(not(target_has_atomic = "64"), feature = "portable-atomic"))]
+pub type Native = Portable64;
+
+/// Backing selected for the target
+///
+/// See [choosing the backing](index.html#choosing-the-backing) for the measurements behind the order.
+#[cfg(all(
+    not(target_has_atomic = "64"),
+    not(feature = "portable-atomic"),
+    target_has_atomic = "32",
+))]
+pub type Native = Lanes32;
+
+/// Backing selected for the target
+///
+/// See [choosing the backing](index.html#choosing-the-backing) for the measurements behind the order.
+#[cfg(all(not(target_has_atomic = "32"), not(feature = "portable-atomic")))]
+pub type Native = Lanes16;
+
+/// Single `AtomicU64`, where the target supports it
+///
+/// This is lock-free even on some 32bit targets, e.g. with `cmpxchg8b` on x86.
+/// Lanes are only used where it's not available, as their writers serialize
+/// on the sequence lock. Splitting the value into lanes without it
+/// tears reads and races the carries between lanes,
+/// so the lanes can't be lock-free.
+#[cfg(target_has_atomic = "64")]
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct Atomic64(AtomicU64);
+
+#[cfg(target_has_atomic = "64")]
+impl Atomic64 {
+    pub(crate) const fn new(num: u64) -> Self {
+        Atomic64(AtomicU64::new(num))
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const u64 {
+        self.0.as_ptr()
+    }
+}
+
+#[cfg(all(feature = "unsafe_bitops", target_has_atomic = "64"))]
+impl Atomic64 {
+    pub(crate) fn as_atomic(&self) -> &AtomicU64 {
+        &self.0
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+impl sealed::Sealed for Atomic64 {}
+
+#[cfg(target_has_atomic = "64")]
+impl Backing for Atomic64 {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Atomic64::new(0);
+
+    #[inline]
+    fn with_value(num: u64) -> Self {
+        Atomic64::new(num)
+    }
+
+    #[inline]
+    fn load(&self) -> u64 {
+        self.0.load(ORDER)
+    }
+
+    #[inline]
+    fn load_acquire(&self) -> u64 {
+        self.0.load(ACQUIRE)
+    }
+
+    #[inline]
+    fn fetch_add(&self, n: u64) -> u64 {
+        self.0.fetch_add(n, ORDER)
+    }
+
+    #[inline]
+    fn fetch_add_ordered(&self, n: u64, order: Ordering) -> u64 {
+        self.0.fetch_add(n, order)
+    }
+
+    #[inline]
+    fn swap(&self, value: u64) -> u64 {
+        self.0.swap(value, ORDER)
+    }
+
+    #[inline]
+    fn fetch_update<F>(&self, f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>
+    {
+        self.0.fetch_update(ORDER, ORDER, f)
+    }
+
+    #[inline]
+    fn fetch_update_weak<F>(&self, mut f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>
+    {
+        let mut current = self.0.load(ORDER);
+
+        while let Some(new) = f(current) {
+            match self.0.compare_exchange_weak(current, new, ORDER, ORDER) {
+                Ok(prev) => return Ok(prev),
+                Err(actual) => current = actual,
+            }
+        }
+
+        Err(current)
+    }
+    #[inline]
+    fn compare_exchange(&self, current: u64, new: u64) -> Result<u64, u64> {
+        self.0.compare_exchange(current, new, ORDER, ORDER)
+    }
+
+    #[inline]
+    fn fetch_max(&self, value: u64) -> u64 {
+        self.0.fetch_max(value, ORDER)
+    }
+
+    #[inline]
+    fn fetch_min(&self, value: u64) -> u64 {
+        self.0.fetch_min(value, ORDER)
+    }
+}
+
+/// Single `portable_atomic::AtomicU64`, with the `portable-atomic` feature
+///
+/// It's lock-free wherever `portable-atomic` can make it so, e.g. with
+/// the 64bit atomic instructions the std doesn't expose on some targets,
+/// or by disabling interrupts on single-core targets with its `critical-section`
+/// or `unsafe-assume-single-core` features.
+/// On targets with native 64bit atomics it's the same as `Atomic64`.
+#[cfg(feature = "portable-atomic")]
+#[derive(Debug)]
+pub struct Portable64(PortableAtomicU64);
+
+#[cfg(feature = "portable-atomic")]
+impl Portable64 {
+    pub(crate) const fn new(num: u64) -> Self {
+        Portable64(PortableAtomicU64::new(num))
+    }
+}
+
+#[cfg(feature = "portable-atomic")]
+impl sealed::Sealed for Portable64 {}
+
+#[cfg(feature = "portable-atomic")]
+impl Backing for Portable64 {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Portable64::new(0);
+
+    #[inline]
+    fn with_value(num: u64) -> Self {
+        Portable64::new(num)
+    }
+
+    #[inline]
+    fn load(&self) -> u64 {
+        self.0.load(ORDER)
+    }
+
+    #[inline]
+    fn load_acquire(&self) -> u64 {
+        self.0.load(ACQUIRE)
+    }
+
+    #[inline]
+    fn fetch_add(&self, n: u64) -> u64 {
+        self.0.fetch_add(n, ORDER)
+    }
+
+    #[inline]
+    fn fetch_add_ordered(&self, n: u64, order: Ordering) -> u64 {
+        self.0.fetch_add(n, order)
+    }
+
+    #[inline]
+    fn swap(&self, value: u64) -> u64 {
+        self.0.swap(value, ORDER)
+    }
+
+    #[inline]
+    fn fetch_update<F>(&self, f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>
+    {
+        self.0.fetch_update(ORDER, ORDER, f)
+    }
+
+    #[inline]
+    fn fetch_update_weak<F>(&self, mut f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>
+    {
+        let mut current = self.0.load(ORDER);
+
+        while let Some(new) = f(current) {
+            match self.0.compare_exchange_weak(current, new, ORDER, ORDER) {
+                Ok(prev) => return Ok(prev),
+                Err(actual) => current = actual,
+            }
+        }
+
+        Err(current)
+    }
+    #[inline]
+    fn compare_exchange(&self, current: u64, new: u64) -> Result<u64, u64> {
+        self.0.compare_exchange(current, new, ORDER, ORDER)
+    }
+
+    #[inline]
+    fn fetch_max(&self, value: u64) -> u64 {
+        self.0.fetch_max(value, ORDER)
+    }
+
+    #[inline]
+    fn fetch_min(&self, value: u64) -> u64 {
+        self.0.fetch_min(value, ORDER)
+    }
+}
+
+/// Plain `u64` guarded by a spin lock, with the `spin` feature
+///
+/// Every read and write takes the lock, so it's obviously correct
+/// but not lock-free: a preempted writer stalls every other thread, and readers
+/// contend with each other unlike the lanes' sequence lock.
+/// For those who prefer simplicity over lock-freedom on the targets
+/// without 64bit atomics, or to cross-check the other backings.
+#[cfg(feature = "spin")]
+#[derive(Debug)]
+pub struct Locked64(SpinMutex<u64>);
+
+#[cfg(feature = "spin")]
+impl Locked64 {
+    pub(crate) const fn new(num: u64) -> Self {
+        Locked64(SpinMutex::new(num))
+    }
+}
+
+#[cfg(feature = "spin")]
+impl sealed::Sealed for Locked64 {}
+
+/// The lock already acquires and releases, like the lanes' sequence lock
+#[cfg(feature = "spin")]
+impl Backing for Locked64 {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Locked64::new(0);
+
+    fn with_value(num: u64) -> Self {
+        Locked64::new(num)
+    }
+
+    fn load(&self) -> u64 {
+        *self.0.lock()
+    }
+
+    /// Fails while a writer holds the lock, instead of spinning
+    fn try_load(&self) -> Option<u64> {
+        self.0.try_lock().map(|num| *num)
+    }
+
+    #[cfg(test)]
+    fn with_write_held<T, F: FnOnce() -> T>(&self, f: F) -> Option<T> {
+        let _num = self.0.lock();
+        Some(f())
+    }
+
+    /// Counts the failed attempts to take the lock
+    #[cfg(feature = "diagnostics")]
+    fn load_with_retries(&self) -> (u64, u32) {
+        let mut retries = 0u32;
+
+        loop {
+            if let Some(num) = self.0.try_lock() {
+                return (*num, retries);
+            }
+
+            retries = retries.saturating_add(1);
+            core::hint::spin_loop();
+        }
+    }
+
+    fn fetch_add(&self, n: u64) -> u64 {
+        let mut num = self.0.lock();
+        let prev = *num;
+        *num = prev.wrapping_add(n);
+        prev
+    }
+
+    fn swap(&self, value: u64) -> u64 {
+        mem::replace(&mut *self.0.lock(), value)
+    }
+
+    /// `f` runs outside of the lock, so it can panic or read the counter itself.
+    /// The store happens only if the value is still the one `f` saw.
+    fn fetch_update<F>(&self, mut f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>
+    {
+        let mut prev = self.load();
+
+        loop {
+            let next = match f(prev) {
+                Some(next) => next,
+                None => return Err(prev),
+            };
+
+            let mut num = self.0.lock();
+            if *num == prev {
+                *num = next;
+                return Ok(prev);
+            }
+            prev = *num;
+        }
+    }
+}
+
+/// Two `AtomicU32` lanes guarded by a sequence lock
+#[cfg(target_has_atomic = "32")]
+#[derive(Debug)]
+pub struct Lanes32 {
+    seq: SeqLock,
+    n1: AtomicU32,
+    n2: AtomicU32,
+}
+
+#[cfg(target_has_atomic = "32")]
+impl Lanes32 {
+    const LANES: usize = 2;
+    const LANE_BITS: usize = mem::size_of::<AtomicU32>() * 8;
+
+    pub(crate) const fn new(num: u64) -> Self {
+        Lanes32 {
+            seq: SeqLock::new(),
+            n1: AtomicU32::new(num as u32),
+            n2: AtomicU32::new((num >> 32) as u32),
+        }
+    }
+
+    fn load_lanes(&self) -> u64 {
+        let mut count = 0u64;
+        count += self.n2.load(ORDER) as u64;
+        count <<= 32;
+        count += self.n1.load(ORDER) as u64;
+
+        count
+    }
+
+    fn store_lanes(&self, num: u64) {
+        self.n1.store(num as u32, ORDER);
+        self.n2.store((num >> 32) as u32, ORDER);
+    }
+}
+
+// Lanes must cover exactly 64 bits, or values silently lose or duplicate bits.
+// The size check ties `LANES` to the actual fields.
+#[cfg(target_has_atomic = "32")]
+const _: () = assert!(Lanes32::LANES * Lanes32::LANE_BITS == 64);
+#[cfg(target_has_atomic = "32")]
+const _: () = assert!(
+    mem::size_of::<Lanes32>() == mem::size_of::<SeqLock>() + Lanes32::LANES * mem::size_of::<AtomicU32>()
+);
+
+#[cfg(target_has_atomic = "32")]
+impl sealed::Sealed for Lanes32 {}
+
+#[cfg(target_has_atomic = "32")]
+impl Backing for Lanes32 {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Lanes32::new(0);
+
+    fn with_value(num: u64) -> Self {
+        Lanes32::new(num)
+    }
+
+    fn load(&self) -> u64 {
+        self.seq.read(|| self.load_lanes())
+    }
+
+    fn load_approx(&self) -> u64 {
+        self.load_lanes()
+    }
+
+    fn try_load(&self) -> Option<u64> {
+        self.seq.try_read(|| self.load_lanes())
+    }
+
+    #[cfg(test)]
+    fn with_write_held<T, F: FnOnce() -> T>(&self, f: F) -> Option<T> {
+        Some(self.seq.write(f))
+    }
+
+    fn load_with_generation(&self) -> (u64, u64) {
+        let (num, seq) = self.seq.read_with_seq(|| self.load_lanes());
+        (num, (seq / 2) as u64)
+    }
+
+    fn load_within(&self, max_retries: u32) -> Result<u64, u64> {
+        self.seq.read_within(max_retries, || self.load_lanes())
+    }
+
+    #[cfg(feature = "diagnostics")]
+    fn load_with_retries(&self) -> (u64, u32) {
+        self.seq.read_with_retries(|| self.load_lanes())
+    }
+
+    fn fetch_add(&self, n: u64) -> u64 {
+        self.seq.write(|| {
+            let prev = self.load_lanes();
+            self.store_lanes(prev.wrapping_add(n));
+            prev
+        })
+    }
+
+    fn swap(&self, value: u64) -> u64 {
+        self.seq.write(|| {
+            let prev = self.load_lanes();
+            self.store_lanes(value);
+            prev
+        })
+    }
+
+    /// `f` runs outside of the lock, so it can panic or read the counter itself.
+    /// The store is retried if another writer got in between.
+    fn fetch_update<F>(&self, mut f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>
+    {
+        loop {
+            let (prev, seq) = self.seq.read_with_seq(|| self.load_lanes());
+            let next = match f(prev) {
+                Some(next) => next,
+                None => return Err(prev),
+            };
+
+            if self.seq.write_if_unchanged(seq, || self.store_lanes(next)).is_some() {
+                return Ok(prev);
+            }
+        }
+    }
+}
+
+/// Four `AtomicU16` lanes guarded by a sequence lock
+#[derive(Debug)]
+pub struct Lanes16 {
+    seq: SeqLock,
+    n1: AtomicU16,
+    n2: AtomicU16,
+    n3: AtomicU16,
+    n4: AtomicU16,
+}
+
+impl Lanes16 {
+    const LANES: usize = 4;
+    const LANE_BITS: usize = mem::size_of::<AtomicU16>() * 8;
+
+    pub(crate) const fn new(num: u64) -> Self {
+        Lanes16 {
+            seq: SeqLock::new(),
+            n1: AtomicU16::new(num as u16),
+            n2: AtomicU16::new((num >> 16) as u16),
+            n3: AtomicU16::new((num >> 32) as u16),
+            n4: AtomicU16::new((num >> 48) as u16),
+        }
+    }
+
+    fn load_lanes(&self) -> u64 {
+        let mut count = 0u64;
+        count += self.n4.load(ORDER) as u64;
+        count <<= 16;
+        count += self.n3.load(ORDER) as u64;
+        count <<= 16;
+        count += self.n2.load(ORDER) as u64;
+        count <<= 16;
+        count += self.n1.load(ORDER) as u64;
+
+        count
+    }
+
+    fn store_lanes(&self, num: u64) {
+        self.n1.store(num as u16, ORDER);
+        self.n2.store((num >> 16) as u16, ORDER);
+        self.n3.store((num >> 32) as u16, ORDER);
+        self.n4.store((num >> 48) as u16, ORDER);
+    }
+}
+
+const _: () = assert!(Lanes16::LANES * Lanes16::LANE_BITS == 64);
+const _: () = assert!(
+    mem::size_of::<Lanes16>() == mem::size_of::<SeqLock>() + Lanes16::LANES * mem::size_of::<AtomicU16>()
+);
+
+impl sealed::Sealed for Lanes16 {}
+
+impl Backing for Lanes16 {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Lanes16::new(0);
+
+    fn with_value(num: u64) -> Self {
+        Lanes16::new(num)
+    }
+
+    fn load(&self) -> u64 {
+        self.seq.read(|| self.load_lanes())
+    }
+
+    fn load_approx(&self) -> u64 {
+        self.load_lanes()
+    }
+
+    fn try_load(&self) -> Option<u64> {
+        self.seq.try_read(|| self.load_lanes())
+    }
+
+    #[cfg(test)]
+    fn with_write_held<T, F: FnOnce() -> T>(&self, f: F) -> Option<T> {
+        Some(self.seq.write(f))
+    }
+
+    fn load_with_generation(&self) -> (u64, u64) {
+        let (num, seq) = self.seq.read_with_seq(|| self.load_lanes());
+        (num, (seq / 2) as u64)
+    }
+
+    fn load_within(&self, max_retries: u32) -> Result<u64, u64> {
+        self.seq.read_within(max_retries, || self.load_lanes())
+    }
+
+    #[cfg(feature = "diagnostics")]
+    fn load_with_retries(&self) -> (u64, u32) {
+        self.seq.read_with_retries(|| self.load_lanes())
+    }
+
+    fn fetch_add(&self, n: u64) -> u64 {
+        self.seq.write(|| {
+            let prev = self.load_lanes();
+            self.store_lanes(prev.wrapping_add(n));
+            prev
+        })
+    }
+
+    fn swap(&self, value: u64) -> u64 {
+        self.seq.write(|| {
+            let prev = self.load_lanes();
+            self.store_lanes(value);
+            prev
+        })
+    }
+
+    /// `f` runs outside of the lock, so it can panic or read the counter itself.
+    /// The store is retried if another writer got in between.
+    fn fetch_update<F>(&self, mut f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>
+    {
+        loop {
+            let (prev, seq) = self.seq.read_with_seq(|| self.load_lanes());
+            let next = match f(prev) {
+                Some(next) => next,
+                None => return Err(prev),
+            };
+
+            if self.seq.write_if_unchanged(seq, || self.store_lanes(next)).is_some() {
+                return Ok(prev);
+            }
+        }
+    }
+}
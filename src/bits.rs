@@ -0,0 +1,76 @@
+//! Bit-level views of a counter's value.
+
+use backing::Backing;
+use Counter64;
+
+/// A single read of a counter's value, for asking several questions about its bits
+///
+/// Created by [`Counter64::bits_snapshot`](struct.Counter64.html#method.bits_snapshot).
+/// Every method answers about the same value, unlike separate reads of the counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitsView(u64);
+
+impl BitsView {
+    /// The value this view was taken of
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    /// Number of ones in the binary representation
+    pub fn count_ones(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Number of zeros in the binary representation
+    pub fn count_zeros(self) -> u32 {
+        self.0.count_zeros()
+    }
+
+    /// Number of trailing zeros in the binary representation, 64 for 0
+    pub fn trailing_zeros(self) -> u32 {
+        self.0.trailing_zeros()
+    }
+
+    /// Number of leading zeros in the binary representation, 64 for 0
+    pub fn leading_zeros(self) -> u32 {
+        self.0.leading_zeros()
+    }
+}
+
+impl<B: Backing> Counter64<B> {
+    /// Read counter's current value once as a [`BitsView`](struct.BitsView.html)
+    pub fn bits_snapshot(&self) -> BitsView {
+        BitsView(self.get())
+    }
+
+    /// Number of ones in the binary representation of counter's current value
+    pub fn count_ones(&self) -> u32 {
+        self.bits_snapshot().count_ones()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Counter;
+
+    #[test]
+    fn test_bits_snapshot() {
+        for &num in &[0, 1, 0b1011_0000, u32::MAX as u64 + 1, u64::MAX, 1 << 63] {
+            let counter = unsafe { Counter::with_init(num) };
+            let view = counter.bits_snapshot();
+
+            assert_eq!(view.value(), num);
+            assert_eq!(view.count_ones(), num.count_ones());
+            assert_eq!(view.count_zeros(), num.count_zeros());
+            assert_eq!(view.trailing_zeros(), num.trailing_zeros());
+            assert_eq!(view.leading_zeros(), num.leading_zeros());
+            assert_eq!(counter.count_ones(), num.count_ones());
+        }
+
+        let counter = unsafe { Counter::with_init(0b1011_0000) };
+        let view = counter.bits_snapshot();
+        counter.incr();
+        assert_eq!((view.count_ones(), view.trailing_zeros()), (3, 4));
+        assert_eq!(counter.count_ones(), 4);
+    }
+}
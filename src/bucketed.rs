@@ -0,0 +1,169 @@
+//! Fixed set of related counters, e.g. latency buckets.
+
+use backing::Backing;
+use Counter;
+use Counter64;
+use COUNTER_INIT;
+
+/// Increase the bucket counter which `value` falls into, and return its previous value
+///
+/// `boundaries` must be sorted ascending, and there must be one more bucket than
+/// boundaries. Bucket `i` counts values in `boundaries[i - 1]..boundaries[i]`,
+/// the first bucket values below `boundaries[0]` and the last, overflow, bucket
+/// values from the last boundary up. The bucket is found by binary search.
+///
+/// # Panics
+///
+/// Panics if `buckets.len() != boundaries.len() + 1`.
+pub fn histogram_into<B: Backing>(buckets: &[Counter64<B>], boundaries: &[u64], value: u64) -> u64 {
+    assert_eq!(
+        buckets.len(),
+        boundaries.len() + 1,
+        "histogram needs one more bucket than boundaries",
+    );
+
+    let i = boundaries.partition_point(|&boundary| boundary <= value);
+    buckets[i].incr()
+}
+
+/// `N` counters indexed by bucket, a minimal histogram building block
+#[derive(Debug)]
+pub struct BucketedCounter<const N: usize> {
+    buckets: [Counter; N],
+}
+
+impl<const N: usize> Default for BucketedCounter<N> {
+    fn default() -> Self {
+        BucketedCounter::new()
+    }
+}
+
+impl<const N: usize> BucketedCounter<N> {
+    /// Create new bucketed counter with every bucket from 0
+    pub const fn new() -> Self {
+        BucketedCounter {
+            buckets: [COUNTER_INIT; N],
+        }
+    }
+
+    /// Get counter of the bucket `i`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= N`.
+    pub fn bucket(&self, i: usize) -> &Counter {
+        &self.buckets[i]
+    }
+
+    /// Increase counter of the bucket `i` by 1, and return its previous value
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= N`.
+    pub fn incr_bucket(&self, i: usize) -> u64 {
+        self.buckets[i].incr()
+    }
+
+    /// Increase counter of the bucket which `bucket_fn` maps `value` to,
+    /// and return its previous value
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket_fn` returns index `>= N`.
+    pub fn record<F: FnOnce(u64) -> usize>(&self, value: u64, bucket_fn: F) -> u64 {
+        self.incr_bucket(bucket_fn(value))
+    }
+
+    /// Read every bucket's current value
+    ///
+    /// Each value is individually consistent, but not across the buckets.
+    pub fn snapshot(&self) -> [u64; N] {
+        ::get_array(&self.buckets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Buckets of `[0, 10)`, `[10, 100)`, `[100, 1000)` and the rest
+    fn decade(value: u64) -> usize {
+        match value {
+            0..=9 => 0,
+            10..=99 => 1,
+            100..=999 => 2,
+            _ => 3,
+        }
+    }
+
+    #[test]
+    fn test_record_distribution() {
+        let buckets = BucketedCounter::<4>::new();
+
+        for value in 0..2000 {
+            buckets.record(value, decade);
+        }
+
+        assert_eq!(buckets.snapshot(), [10, 90, 900, 1000]);
+        assert_eq!(buckets.bucket(2).get(), 900);
+    }
+
+    #[test]
+    fn test_multithread_incr_bucket() {
+        static BUCKETS: BucketedCounter<3> = BucketedCounter::new();
+
+        let handles: Vec<_> = (0..6)
+            .map(|i| {
+                thread::spawn(move|| {
+                    for _ in 0..1000 {
+                        BUCKETS.incr_bucket(i % 3);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(BUCKETS.snapshot(), [2000, 2000, 2000]);
+    }
+
+    #[test]
+    fn test_histogram_into_matches_linear_scan() {
+        let boundaries = [10, 20, 50, 100, 1000];
+        let buckets: Vec<Counter> = (0..=boundaries.len()).map(|_| Counter::new()).collect();
+        let mut expected = [0u64; 6];
+
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let values = (0..5000).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % 2000
+        });
+
+        for value in values.chain(vec![0, 9, 10, 999, 1000, u64::MAX]) {
+            let i = boundaries.iter().take_while(|&&boundary| boundary <= value).count();
+            expected[i] += 1;
+            histogram_into(&buckets, &boundaries, value);
+        }
+
+        let counts: Vec<u64> = buckets.iter().map(Counter::get).collect();
+        assert_eq!(counts, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_histogram_into_bucket_count_mismatch() {
+        let buckets = [COUNTER_INIT; 2];
+        histogram_into(&buckets, &[10, 20], 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_range_bucket() {
+        BucketedCounter::<2>::new().incr_bucket(2);
+    }
+}
@@ -0,0 +1,117 @@
+//! Reading a counter's progress since the last look.
+
+use backing::Backing;
+use Counter64;
+
+/// Reader which remembers the value it observed last,
+/// to report how much the counter increased since then
+///
+/// Created by [`Counter64::delta_reader`](struct.Counter64.html#method.delta_reader).
+/// Each reader keeps its own last value, so independent consumers,
+/// e.g. several metrics exporters, don't steal each other's increments.
+#[derive(Debug, Clone)]
+pub struct DeltaReader<'a, B: Backing> {
+    counter: &'a Counter64<B>,
+    last: u64,
+}
+
+impl<'a, B: Backing> DeltaReader<'a, B> {
+    /// Get how much the counter increased since the previous call,
+    /// or since the reader was created
+    ///
+    /// Uses wrapping subtraction, so it's correct across a wrap around
+    /// as long as the counter doesn't go around entirely between two calls.
+    pub fn next_delta(&mut self) -> u64 {
+        let current = self.counter.get();
+        let delta = current.wrapping_sub(self.last);
+        self.last = current;
+        delta
+    }
+
+    /// Value the reader observed last
+    pub fn last(&self) -> u64 {
+        self.last
+    }
+}
+
+/// Advance every reader, and return their deltas in order
+///
+/// For scrape loops collecting many counters at once. Like
+/// [`snapshot_all`](fn.snapshot_all.html), each counter is read individually.
+#[cfg(feature = "std")]
+pub fn next_deltas<B: Backing>(readers: &mut [DeltaReader<'_, B>]) -> Vec<u64> {
+    readers.iter_mut().map(DeltaReader::next_delta).collect()
+}
+
+impl<B: Backing> Counter64<B> {
+    /// Create a reader of the increments from now on, see [`DeltaReader`](struct.DeltaReader.html)
+    pub fn delta_reader(&self) -> DeltaReader<'_, B> {
+        DeltaReader {
+            counter: self,
+            last: self.get(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Counter;
+
+    #[test]
+    fn test_next_delta() {
+        let counter = Counter::new();
+        counter.add(10);
+
+        let mut reader = counter.delta_reader();
+        assert_eq!(reader.next_delta(), 0);
+
+        counter.add(5);
+        assert_eq!(reader.next_delta(), 5);
+
+        let mut other = counter.delta_reader();
+        counter.incr();
+        counter.add(100);
+        assert_eq!(reader.next_delta(), 101);
+        assert_eq!(reader.next_delta(), 0);
+        assert_eq!(other.next_delta(), 101);
+        assert_eq!(reader.last(), 116);
+    }
+
+    #[test]
+    fn test_next_delta_across_wrap() {
+        let counter = unsafe { Counter::with_init(u64::MAX - 2) };
+        let mut reader = counter.delta_reader();
+
+        counter.wrapping_add(5);
+        assert_eq!(counter.get(), 2);
+        assert_eq!(reader.next_delta(), 5);
+
+        counter.add(3);
+        assert_eq!(reader.next_delta(), 3);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_next_deltas() {
+        use super::next_deltas;
+
+        let counters: [Counter; 4] = Default::default();
+        counters[2].add(50);
+
+        let mut readers: Vec<_> = counters.iter().map(Counter::delta_reader).collect();
+        assert_eq!(next_deltas(&mut readers), vec![0; 4]);
+
+        for (i, counter) in counters.iter().enumerate() {
+            counter.add(i as u64 * 10);
+        }
+        counters[0].incr();
+        assert_eq!(next_deltas(&mut readers), vec![1, 10, 20, 30]);
+
+        counters[3].add(7);
+        assert_eq!(next_deltas(&mut readers), vec![0, 0, 0, 7]);
+        assert_eq!(next_deltas(&mut readers), vec![0; 4]);
+        assert_eq!(readers[2].last(), 70);
+
+        assert_eq!(next_deltas::<::backing::Native>(&mut []), Vec::<u64>::new());
+    }
+}
@@ -0,0 +1,53 @@
+//! Contention metrics of a counter.
+
+use core::sync::atomic::{AtomicUsize, Ordering as O};
+
+/// How often operations on a counter had to retry, see
+/// [`Counter64::contention_stats`](struct.Counter64.html#method.contention_stats)
+///
+/// Both are cumulative since the counter was created, and wrap around
+/// on overflow of `usize`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContentionStats {
+    /// Retries of compare-and-swap loops, like `checked_add` or `fetch_update_weak`,
+    /// after another thread modified the counter in between.
+    pub cas_retries: u64,
+    /// Retries of consistent reads, like `get`, interleaved with a writer.
+    /// Only the multi-word backings retry reads.
+    pub read_retries: u64,
+}
+
+/// Retry counts of a counter, only recorded with the `diagnostics` feature
+#[derive(Debug)]
+pub struct Diagnostics {
+    cas_retries: AtomicUsize,
+    read_retries: AtomicUsize,
+}
+
+impl Diagnostics {
+    pub const fn new() -> Self {
+        Diagnostics {
+            cas_retries: AtomicUsize::new(0),
+            read_retries: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn record_cas_retries(&self, retries: u32) {
+        if retries != 0 {
+            self.cas_retries.fetch_add(retries as usize, O::Relaxed);
+        }
+    }
+
+    pub fn record_read_retries(&self, retries: u32) {
+        if retries != 0 {
+            self.read_retries.fetch_add(retries as usize, O::Relaxed);
+        }
+    }
+
+    pub fn stats(&self) -> ContentionStats {
+        ContentionStats {
+            cas_retries: self.cas_retries.load(O::Relaxed) as u64,
+            read_retries: self.read_retries.load(O::Relaxed) as u64,
+        }
+    }
+}
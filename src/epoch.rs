@@ -0,0 +1,162 @@
+//! IDs made of a rotating epoch and a counter within it.
+
+use Counter;
+
+/// Counter of IDs within an epoch, packed as `(epoch << SHIFT) | count`
+///
+/// [`rotate_epoch`](#method.rotate_epoch) advances the epoch and restarts
+/// the count from 0. Both live in a single counter, so minting and rotating
+/// never interleave: every ID belongs to exactly one epoch and none repeats
+/// until the epoch wraps around.
+#[derive(Debug)]
+pub struct EpochCounter<const SHIFT: u32 = 32> {
+    packed: Counter,
+}
+
+/// Same as [`new`](#method.new), including its check of `SHIFT`
+impl<const SHIFT: u32> Default for EpochCounter<SHIFT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const SHIFT: u32> EpochCounter<SHIFT> {
+    const COUNT_MASK: u64 = (1 << SHIFT) - 1;
+
+    /// Create new epoch counter at epoch 0, count 0
+    pub const fn new() -> Self {
+        assert!(SHIFT > 0 && SHIFT < 64, "SHIFT must leave bits for both the epoch and the count");
+
+        EpochCounter {
+            packed: Counter::new(),
+        }
+    }
+
+    /// Mint the next ID of the current epoch
+    ///
+    /// Returns `None` if the epoch ran out of its `2^SHIFT - 1` IDs,
+    /// in which case it should be rotated. The count stops at the all-ones
+    /// value, which marks the exhausted epoch instead of being minted,
+    /// so it never carries into the epoch bits.
+    pub fn next_id(&self) -> Option<u64> {
+        self.packed
+            .fetch_update(|packed| {
+                if packed & Self::COUNT_MASK == Self::COUNT_MASK {
+                    None
+                } else {
+                    Some(packed + 1)
+                }
+            })
+            .ok()
+    }
+
+    /// Advance to the next epoch restarting the count, and return the new epoch
+    ///
+    /// The epoch wraps around after `u64::MAX >> SHIFT`.
+    pub fn rotate_epoch(&self) -> u64 {
+        let prev = self.packed
+            .fetch_update(|packed| Some(((packed >> SHIFT) + 1) << SHIFT))
+            .unwrap_or_else(|packed| packed);
+
+        ((prev >> SHIFT) + 1) & (u64::MAX >> SHIFT)
+    }
+
+    /// Get the current `(epoch, count)`, where count is the next ID to be minted in it
+    pub fn current_epoch_value(&self) -> (u64, u64) {
+        let packed = self.packed.get();
+        (packed >> SHIFT, packed & Self::COUNT_MASK)
+    }
+
+    /// Split an ID into its `(epoch, count)`
+    pub fn split_id(id: u64) -> (u64, u64) {
+        (id >> SHIFT, id & Self::COUNT_MASK)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    #[test]
+    fn test_rotation_tags_epoch() {
+        let counter = EpochCounter::<8>::new();
+
+        assert_eq!(counter.next_id(), Some(0));
+        assert_eq!(counter.next_id(), Some(1));
+        assert_eq!(counter.rotate_epoch(), 1);
+        assert_eq!(counter.next_id(), Some(1 << 8));
+        assert_eq!(counter.current_epoch_value(), (1, 1));
+
+        // 2^8 - 1 IDs per epoch, the last one is 254
+        for count in 1..255 {
+            assert_eq!(counter.next_id(), Some(1 << 8 | count));
+        }
+        assert_eq!(counter.current_epoch_value(), (1, 255));
+        assert_eq!(counter.next_id(), None);
+
+        assert_eq!(counter.rotate_epoch(), 2);
+        assert_eq!(EpochCounter::<8>::split_id(counter.next_id().unwrap()), (2, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "SHIFT must leave bits")]
+    fn test_default_checks_shift() {
+        let _ = EpochCounter::<0>::default();
+    }
+
+    #[test]
+    fn test_epoch_wraps_around() {
+        let counter = EpochCounter::<62>::new();
+
+        assert_eq!(counter.rotate_epoch(), 1);
+        assert_eq!(counter.rotate_epoch(), 2);
+        assert_eq!(counter.rotate_epoch(), 3);
+        assert_eq!(counter.rotate_epoch(), 0);
+        assert_eq!(counter.next_id(), Some(0));
+    }
+
+    #[test]
+    fn test_multithread_no_duplicates() {
+        let counter = EpochCounter::<32>::new();
+        let done = AtomicBool::new(false);
+
+        let (ids, rotations) = thread::scope(|s| {
+            let rotator = s.spawn(|| {
+                let mut rotations = 0;
+                while !done.load(Ordering::Relaxed) {
+                    counter.rotate_epoch();
+                    rotations += 1;
+                    thread::yield_now();
+                }
+                rotations
+            });
+
+            let minters: Vec<_> = (0..4)
+                .map(|_| {
+                    s.spawn(|| {
+                        (0..20000)
+                            .filter_map(|_| counter.next_id())
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            let ids: Vec<_> = minters.into_iter().flat_map(|h| h.join().unwrap()).collect();
+            done.store(true, Ordering::Relaxed);
+
+            (ids, rotator.join().unwrap())
+        });
+
+        assert_eq!(ids.len(), 80000);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+
+        let final_epoch = counter.current_epoch_value().0;
+        assert_eq!(final_epoch, rotations);
+        for &id in &ids {
+            assert!(EpochCounter::<32>::split_id(id).0 <= final_epoch);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Errors of the fallible operations.
+
+use core::fmt;
+
+/// Why a `try_` operation of the counter failed
+///
+/// New failure modes may be added, so matches need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CounterError {
+    /// The operation would wrap the counter around `u64::MAX`
+    ///
+    /// Returned by [`try_add`](struct.Counter64.html#method.try_add).
+    /// The counter is left untouched.
+    Overflow,
+    /// The counter's value doesn't fit the requested narrower type
+    ///
+    /// Returned by [`try_get_u32`](struct.Counter64.html#method.try_get_u32).
+    Truncation,
+    /// A writer was updating the lanes of a multi-word backing during the read
+    ///
+    /// Returned by [`try_get`](struct.Counter64.html#method.try_get).
+    /// Never returned on the single-word backing.
+    InconsistentRead,
+    /// A sharded counter was asked for zero shards
+    ///
+    /// Returned by [`AdaptiveCounter::with_shards`](struct.AdaptiveCounter.html#method.with_shards).
+    InvalidShardCount,
+    /// A step of zero was requested
+    ///
+    /// Returned by [`SteppedCounter::with_start_and_step`](struct.SteppedCounter.html#method.with_start_and_step)
+    /// and [`ThresholdNotifier::new`](struct.ThresholdNotifier.html#method.new).
+    InvalidStep,
+}
+
+impl fmt::Display for CounterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CounterError::Overflow => "counter overflowed",
+            CounterError::Truncation => "counter value doesn't fit the target type",
+            CounterError::InconsistentRead => "counter was read while being written",
+            CounterError::InvalidShardCount => "shard count must be nonzero",
+            CounterError::InvalidStep => "step must be nonzero",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for CounterError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(CounterError::Overflow.to_string(), "counter overflowed");
+        assert_eq!(CounterError::Truncation.to_string(), "counter value doesn't fit the target type");
+        assert_eq!(CounterError::InconsistentRead.to_string(), "counter was read while being written");
+        assert_eq!(CounterError::InvalidShardCount.to_string(), "shard count must be nonzero");
+        assert_eq!(CounterError::InvalidStep.to_string(), "step must be nonzero");
+    }
+}
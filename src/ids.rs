@@ -0,0 +1,71 @@
+//! Iterating over IDs handed out by a counter.
+
+use core::iter::FusedIterator;
+
+use backing::Backing;
+use Counter64;
+
+/// Infinite iterator which increases the counter on each `next`,
+/// yielding the previous value
+///
+/// Created by [`Counter64::iter_ids`](struct.Counter64.html#method.iter_ids)
+/// or by iterating over `&Counter64`. It never returns `None`,
+/// so use `take` or `break` to stop. Other threads may take IDs in between,
+/// so the yielded IDs are unique until the counter wraps around, but not necessarily contiguous.
+#[derive(Debug)]
+pub struct Ids<'a, B: Backing> {
+    counter: &'a Counter64<B>,
+}
+
+impl<'a, B: Backing> Iterator for Ids<'a, B> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        Some(self.counter.incr())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<'a, B: Backing> FusedIterator for Ids<'a, B> {}
+
+impl<'a, B: Backing> IntoIterator for &'a Counter64<B> {
+    type Item = u64;
+    type IntoIter = Ids<'a, B>;
+
+    fn into_iter(self) -> Ids<'a, B> {
+        self.iter_ids()
+    }
+}
+
+impl<B: Backing> Counter64<B> {
+    /// Iterate over IDs, increasing the counter by 1 for each
+    ///
+    /// It's infinite, see [`Ids`](struct.Ids.html).
+    pub fn iter_ids(&self) -> Ids<'_, B> {
+        Ids { counter: self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Counter;
+
+    #[test]
+    fn test_into_iter_take() {
+        let counter = Counter::new();
+
+        assert_eq!((&counter).into_iter().take(5).collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+        assert_eq!(counter.get(), 5);
+
+        for id in &counter {
+            if id == 7 {
+                break;
+            }
+        }
+        assert_eq!(counter.get(), 8);
+        assert_eq!(counter.iter_ids().nth(2), Some(10));
+    }
+}
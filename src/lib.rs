@@ -1,190 +1,1416 @@
 //! 64bit increase-only atomic counter for everywhere.
 //!
-//! `Counter64` uses single `AtomicUsize` when it can serve `u64`.
-//! Otherwise, it fallbacks to use multiple `AtomicUsize` and combine them.
+//! `Counter64` uses single `AtomicU64` when the target supports it.
+//! Otherwise, it fallbacks to use multiple smaller atomics and combine them,
+//! guarded by a sequence lock so readers never observe a torn value.
+//!
+//! [`Counter`](type.Counter.html) uses the backing selected for the target.
+//! The multi-word fallbacks can be forced on any host by naming them explicitly,
+//! e.g. `Counter64<backing::Lanes32>`, which is how they're tested.
+//!
+//! | target                                     | backing    |
+//! |--------------------------------------------|------------|
+//! | 64bit atomics, incl. x86, arm64 and wasm32 | `Atomic64` |
+//! | only up to 32bit atomics                   | `Lanes32`  |
+//! | only up to 16bit atomics                   | `Lanes16`  |
+//!
+//! With the `portable-atomic` feature, targets without 64bit atomics
+//! use `Portable64` instead of the lanes. With the `spin` feature,
+//! `Locked64` can be named to use a spin lock instead.
+//!
+//! # Naming convention
+//!
+//! Methods which modify the counter return its previous value,
+//! like `fetch_*` methods of the std atomics.
+//!
+//! - Methods suffixed with `_return_new` return the value after the operation instead.
+//! - Plain arithmetic like `add` and `incr` panics on overflow in debug builds
+//!   and wraps around in release builds, like the integer arithmetic.
+//! - Methods prefixed with `wrapping_` always wrap around on overflow.
+//! - Methods prefixed with `checked_` fail on overflow instead of wrapping around.
+//!   They return `None` and leave the counter untouched.
+//! - Methods prefixed with `saturating_` stop at `u64::MAX` instead of wrapping around.
+//! - `overflowing_add` mirrors `u64::overflowing_add`, returning the new value
+//!   and whether it wrapped around.
+//! - Methods prefixed with `try_` return a [`CounterError`](enum.CounterError.html)
+//!   describing why they failed.
+//!
+//! | returns  | plain            | wrapping         | checked                  |
+//! |----------|------------------|------------------|--------------------------|
+//! | previous | `incr`, `add`    | `wrapping_add`   | `checked_incr`, `checked_add` |
+//! | new      | `incr_return_new`, `add_return_new` |  | `checked_incr_return_new`, `checked_add_return_new` |
+//!
+//! # Allocating ranges
+//!
+//! When the counter hands out IDs, the previous value is the one the caller owns
+//! and the new value is the next free one.
+//!
+//! - `incr` returns the ID you own, `incr_return_new` the next free ID, one past it.
+//! - `add(n)` returns the start of the `n` IDs you own, `prev..prev + n`,
+//!   and `add_return_new(n)` returns their end, which is the next free ID.
+//!
+//! Other threads may have claimed the next free ID by the time it's returned.
+//! It's where this allocation ended, not a reservation.
+//!
+//! # Synchronization
+//!
+//! Counting alone doesn't synchronize anything, so `get` and `add`
+//! are `Relaxed` and **must not** be used to publish other data.
+//! A reader which sees the counter advanced by `add` may still see stale values
+//! of whatever the writer stored before it.
+//!
+//! To use the counter as a sequence number guarding some data,
+//! write with [`add_release`](struct.Counter64.html#method.add_release)
+//! or [`incr_release`](struct.Counter64.html#method.incr_release)
+//! and read with [`get_acquire`](struct.Counter64.html#method.get_acquire).
+//! Everything written before the release is visible after an acquire
+//! which observes its value.
+//!
+//! The pairing is checked under [Miri](https://github.com/rust-lang/miri) by
+//! `tests/litmus.rs`, which passes a non-atomic message through the counter
+//! and reports a data race if either ordering is weakened. It isn't a
+//! [loom](https://docs.rs/loom) test because loom needs its own atomics,
+//! whose constructors aren't `const`, while the backings are built in `const`
+//! context for [`Counter64::new`](struct.Counter64.html#method.new) and
+//! [`new_const!`](macro.new_const.html). Miri checks the std atomics as they are,
+//! exploring fewer interleavings per run than loom but on the shipped code.
+//!
+//! # Compared to `AtomicU64`
+//!
+//! Where the operations overlap, `Counter64` behaves exactly like `AtomicU64`
+//! on every backing, including the multi-word ones.
+//!
+//! | `AtomicU64`        | `Counter64`        |
+//! |--------------------|--------------------|
+//! | `load`             | `get`              |
+//! | `swap`             | `swap`             |
+//! | `fetch_add`        | `wrapping_add`     |
+//! | `compare_exchange` | `compare_exchange` |
+//! | `fetch_max`        | `fetch_max`        |
+//!
+//! On the single-word backing, `From` converts between the two by value,
+//! for migrating either way.
+//!
+//! ```
+//! use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+//! use counter64::{backing::Lanes16, Counter64};
+//!
+//! let atomic = AtomicU64::new(0);
+//! let counter = Counter64::<Lanes16>::new();
+//!
+//! assert_eq!(counter.wrapping_add(u64::MAX), atomic.fetch_add(u64::MAX, Relaxed));
+//! assert_eq!(counter.wrapping_add(2), atomic.fetch_add(2, Relaxed));
+//! assert_eq!(counter.compare_exchange(1, 7), atomic.compare_exchange(1, 7, Relaxed, Relaxed));
+//! assert_eq!(counter.compare_exchange(1, 9), atomic.compare_exchange(1, 9, Relaxed, Relaxed));
+//! assert_eq!(counter.swap(3), atomic.swap(3, Relaxed));
+//! assert_eq!(counter.get(), atomic.load(Relaxed));
+//! ```
+//!
+//! The differences are intentional:
+//!
+//! - There's no `store`, use `swap`. Setting an arbitrary value isn't counting,
+//!   so the counter only offers the one which shows what was overwritten.
+//! - Orderings aren't taken. Counting is `Relaxed` unless the `seqcst` feature is on,
+//!   and the `_release`/`_acquire` methods cover publishing, see above.
+//! - Operations which decrease the counter, like `fetch_min` and `checked_sub`,
+//!   need the `gauge` feature, and bitwise ones need `unsafe_bitops`.
+//! - Arithmetic has `checked_` and `saturating_` flavors besides wrapping.
+//!
+//! # Features
+//!
+//! - `std` (default): APIs which need the standard library.
+//!   Without it the crate is `no_std`.
+//! - `async`: [`AsyncCounter`](struct.AsyncCounter.html), which tasks can wait on
+//!   with [`wait_until_async`](struct.AsyncCounter.html#method.wait_until_async).
+//!   Plain counters don't pay for it.
+//! - `gauge`: operations which can decrease the counter.
+//! - `adaptive`: [`AdaptiveCounter`](struct.AdaptiveCounter.html), which shards itself under contention.
+//! - `unsafe_bitops`: bitwise operations on the single-word backing.
+//! - `single_thread`: [`LocalCounter`](struct.LocalCounter.html), a `!Sync` counter
+//!   on a `Cell` without any atomic overhead.
+//! - `diagnostics`: [`Counter64::contention_stats`](struct.Counter64.html#method.contention_stats),
+//!   counting retries of the operations under contention.
+//! - `test-util`: assertion helpers like
+//!   [`Counter64::assert_eq_value`](struct.Counter64.html#method.assert_eq_value)
+//!   for the tests of downstream crates.
+//! - `seqcst`: use `SeqCst` for every counting operation instead of `Relaxed`.
+//!   Slower on weakly ordered targets, meant for debugging.
+//! - `portable-atomic`: use [`portable_atomic::AtomicU64`](https://docs.rs/portable-atomic)
+//!   on the targets without 64bit atomics, which makes the counter lock-free
+//!   on nearly every target, e.g. 32bit ARM, MIPS, PowerPC and RISC-V.
+//!   Single-core targets without any atomic CAS, like thumbv6m or riscv32i,
+//!   additionally need its `critical-section` or `unsafe-assume-single-core` feature.
+//! - `spin`: [`backing::Locked64`](backing/struct.Locked64.html), which guards a plain `u64`
+//!   with a spin lock. Simpler than the lanes' sequence lock but not lock-free,
+//!   for when correctness matters more than progress guarantees.
+//! - `rand`: [`Counter64::new_random_start`](struct.Counter64.html#method.new_random_start),
+//!   for IDs which don't reveal how many were issued.
+//! - `bytemuck`: [`CounterPod`](struct.CounterPod.html), a `bytemuck::Pod` snapshot
+//!   of the counter for zero-copy serialization.
 //!
 
-pub use counter::*;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-#[cfg(not(any(
-    target_pointer_width = "8",
-    target_pointer_width = "16",
-    target_pointer_width = "32")))]
-mod counter {
-    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering::Relaxed};
+#[cfg(any(feature = "std", test))]
+extern crate core;
+#[cfg(feature = "portable-atomic")]
+extern crate portable_atomic;
+#[cfg(feature = "bytemuck")]
+extern crate bytemuck;
+#[cfg(feature = "spin")]
+extern crate spin;
+#[cfg(feature = "rand")]
+extern crate rand;
+#[cfg(test)]
+#[macro_use]
+extern crate proptest;
+#[cfg(all(test, feature = "async"))]
+extern crate futures;
 
-    #[derive(Debug)]
-    pub struct Counter(AtomicUsize);
+#[cfg(feature = "adaptive")]
+pub use adaptive::AdaptiveCounter;
+pub use array::CounterArray;
+pub use backing::Backing;
+pub use bits::BitsView;
+pub use bucketed::{histogram_into, BucketedCounter};
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::ContentionStats;
+pub use delta::DeltaReader;
+#[cfg(feature = "std")]
+pub use delta::next_deltas;
+pub use epoch::EpochCounter;
+pub use error::CounterError;
+pub use ids::Ids;
+#[cfg(feature = "single_thread")]
+pub use local::LocalCounter;
+#[cfg(feature = "std")]
+pub use once::OnceCounter;
+#[cfg(feature = "bytemuck")]
+pub use pod::CounterPod;
+#[cfg(feature = "std")]
+pub use rate::EwmaRate;
+#[cfg(feature = "std")]
+pub use registry::Registry;
+pub use resettable::ResettableCounter;
+#[cfg(feature = "std")]
+pub use sample::Sample;
+pub use snapshot::{drain_all, get_array, ratio, relaxed_sum, snapshot_pair};
+pub use stamped::StampedCounter;
+pub use stats::Stats;
+pub use stepped::SteppedCounter;
+pub use threshold::ThresholdNotifier;
+#[cfg(feature = "std")]
+pub use snapshot::{consistent_sum, observe_and_reset_all, snapshot_all};
+#[cfg(feature = "std")]
+pub use throttled::ThrottledCounter;
+#[cfg(feature = "std")]
+pub use timing::TimingGuard;
+#[cfg(feature = "async")]
+pub use wait::{AsyncCounter, WaitUntil};
 
-    pub const COUNTER_INIT: Counter = Counter(ATOMIC_USIZE_INIT);
+#[cfg(feature = "adaptive")]
+mod adaptive;
+mod array;
+pub mod backing;
+mod bits;
+mod bucketed;
+mod delta;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+mod epoch;
+mod error;
+mod ids;
+#[cfg(feature = "single_thread")]
+mod local;
+#[cfg(feature = "std")]
+mod once;
+#[cfg(feature = "bytemuck")]
+mod pod;
+#[cfg(feature = "std")]
+mod rate;
+#[cfg(feature = "std")]
+mod registry;
+mod resettable;
+#[cfg(feature = "std")]
+mod sample;
+mod seqlock;
+mod snapshot;
+mod stamped;
+mod stats;
+mod stepped;
+mod threshold;
+#[cfg(feature = "std")]
+mod throttled;
+#[cfg(feature = "std")]
+mod timing;
+#[cfg(feature = "async")]
+mod wait;
 
-    impl Counter {
-        /// Create new counter from 0
-        pub fn new() -> Self {
-            COUNTER_INIT
-        }
+use core::fmt;
+use core::ops::Range;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+use core::sync::atomic::Ordering;
 
-        pub unsafe fn with_init(num: u64) -> Self {
-            Counter(AtomicUsize::new(num as usize))
-        }
+#[cfg(all(feature = "unsafe_bitops", target_has_atomic = "64"))]
+use backing::ORDER;
+use backing::RELEASE;
 
-        /// Get counter's current value
-        pub fn get(&self) -> u64 {
-            self.0.load(Relaxed) as u64
-        }
+#[cfg(feature = "diagnostics")]
+use diagnostics::Diagnostics;
+
+/// 64bit increase-only atomic counter, stored in `B`
+///
+/// Use [`Counter`](type.Counter.html) unless you want to pick the backing explicitly.
+pub struct Counter64<B: Backing> {
+    inner: B,
+    #[cfg(feature = "diagnostics")]
+    diagnostics: Diagnostics,
+}
 
-        /// Increase counter by 1, and return previous value
-        pub fn incr(&self) -> u64 {
-            self.0.fetch_add(1, Relaxed) as u64
+/// Counter with the backing selected for the target
+pub type Counter = Counter64<backing::Native>;
+
+#[allow(clippy::declare_interior_mutable_const)]
+pub const COUNTER_INIT: Counter = Counter::new();
+
+/// Formats as `Counter64(value)` with the current value,
+/// regardless of the backing, so it reads well inside derived `Debug` output.
+impl<B: Backing> fmt::Debug for Counter64<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Counter64").field(&self.get()).finish()
+    }
+}
+
+impl<B: Backing> Default for Counter64<B> {
+    fn default() -> Self {
+        Counter64::new()
+    }
+}
+
+/// Create a [`Counter`](type.Counter.html) starting from `value`, in `const` context
+///
+/// The `const` counterpart of [`Counter64::with_init`](struct.Counter64.html#method.with_init),
+/// e.g. for a `static` counter which continues from a persisted value.
+/// `value` must be a constant expression. It's safe as nothing can rely on
+/// a `const` or `static` counter before it exists.
+/// It uses the lanes of whichever backing the target selected.
+///
+/// ```
+/// #[macro_use]
+/// extern crate counter64;
+///
+/// use counter64::Counter;
+///
+/// static NEXT_ID: Counter = new_const!(1000);
+///
+/// fn main() {
+///     assert_eq!(NEXT_ID.incr(), 1000);
+/// }
+/// ```
+#[macro_export]
+macro_rules! new_const {
+    ($value:expr) => {{
+        const VALUE: u64 = $value;
+        unsafe { $crate::Counter::__with_init_const(VALUE) }
+    }};
+}
+
+impl Counter64<backing::Native> {
+    #[doc(hidden)]
+    pub const unsafe fn __with_init_const(num: u64) -> Self {
+        Counter64 {
+            inner: backing::Native::new(num),
+            #[cfg(feature = "diagnostics")]
+            diagnostics: Diagnostics::new(),
         }
     }
 }
 
-#[cfg(target_pointer_width = "16")]
-mod counter {
-    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering as O};
-    use std::usize;
+/// Read counter's current value, same as [`get`](struct.Counter64.html#method.get)
+///
+/// Every backing stores the full 64bit value, so this can't fail
+/// and there's no `TryFrom` of its own. The blanket `TryFrom` derived from it
+/// has `Infallible` error. For a narrower type, use
+/// [`try_get_u32`](struct.Counter64.html#method.try_get_u32)
+/// or convert the `u64` itself.
+impl<'a, B: Backing> From<&'a Counter64<B>> for u64 {
+    fn from(counter: &'a Counter64<B>) -> u64 {
+        counter.get()
+    }
+}
 
-    #[derive(Debug)]
-    pub struct Counter {
-        n1: AtomicUsize,
-        n2: AtomicUsize,
+/// Take over the value of a std atomic, for migrating from `AtomicU64`
+#[cfg(target_has_atomic = "64")]
+impl From<AtomicU64> for Counter64<backing::Atomic64> {
+    fn from(atomic: AtomicU64) -> Self {
+        // The atomic may hold anything, but it's the caller's count
+        // carried over, not a new counter starting from an arbitrary value.
+        unsafe { Counter64::with_init(atomic.into_inner()) }
     }
+}
 
-    pub const COUNTER_INIT: Counter = Counter {
-        n1: ATOMIC_USIZE_INIT,
-        n2: ATOMIC_USIZE_INIT,
-    };
+/// Hand over the counter's value to a std atomic, for migrating to `AtomicU64`
+#[cfg(target_has_atomic = "64")]
+impl From<Counter64<backing::Atomic64>> for AtomicU64 {
+    fn from(counter: Counter64<backing::Atomic64>) -> Self {
+        AtomicU64::new(counter.get())
+    }
+}
 
-    impl Counter {
-        /// Create new counter from 0
-        pub fn new() -> Self {
-            COUNTER_INIT
+impl<B: Backing> Counter64<B> {
+    /// Create new counter from 0
+    pub const fn new() -> Self {
+        Counter64 {
+            inner: B::INIT,
+            #[cfg(feature = "diagnostics")]
+            diagnostics: Diagnostics::new(),
         }
+    }
 
-        pub unsafe fn with_init(num: u64) -> Self {
-            Counter {
-                n1: AtomicUsize::new((num & 0xFFFFFFFF) as usize),
-                n2: AtomicUsize::new((num >> 32) as usize),
-            }
+    /// Create new counter from `num`
+    ///
+    /// # Safety
+    ///
+    /// Counter is expected to start from 0 and only increase.
+    /// Caller must ensure no one relies on it.
+    pub unsafe fn with_init(num: u64) -> Self {
+        Counter64 {
+            inner: B::with_value(num),
+            #[cfg(feature = "diagnostics")]
+            diagnostics: Diagnostics::new(),
         }
+    }
+
+    /// Create new counter from a random value below `2^63`
+    ///
+    /// For ID generators whose IDs shouldn't reveal how many were issued before.
+    /// Only the start is random, after that it counts up as usual, so anyone
+    /// who saw two IDs can still tell how many were issued between them,
+    /// and guess the ones nearby. The top bit is left clear so it takes
+    /// at least `2^63` increments to overflow.
+    #[cfg(feature = "rand")]
+    pub fn new_random_start() -> Self {
+        unsafe { Self::with_init(rand::random::<u64>() >> 1) }
+    }
 
-        /// Get counter's current value
-        pub fn get(&self) -> u64 {
-            loop {
-                let n1 = self.n1.load(O::SeqCst);
-                let n2 = self.n2.load(O::SeqCst);
+    /// Create new counter from the `high` and `low` 32bit words of its value
+    ///
+    /// The inverse of [`split_half`](#method.split_half),
+    /// combining them as `(high << 32) | low` like the lanes do.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`with_init`](#method.with_init).
+    pub unsafe fn from_halves(high: u32, low: u32) -> Self {
+        Counter64::with_init((high as u64) << 32 | low as u64)
+    }
 
-                if n1 == self.n1.load(O::SeqCst) {
-                    let mut count = 0u64;
-                    count += n2;
-                    count <<= 32;
-                    count += n1;
+    /// Create new counter from the parts above and below `bit` of its value
+    ///
+    /// The inverse of [`split_at_bit`](#method.split_at_bit),
+    /// combining them as `(high << bit) | low`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bit > 64`, or either part doesn't fit in its side of `bit`.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`with_init`](#method.with_init).
+    pub unsafe fn from_parts(high: u64, low: u64, bit: u32) -> Self {
+        assert!(bit <= 64, "bit position {} is out of range", bit);
+        assert!(low_bits(low, bit) == low, "low part {} doesn't fit below bit {}", low, bit);
+        assert!(
+            high.checked_shr(64 - bit).unwrap_or(0) == 0,
+            "high part {} doesn't fit above bit {}",
+            high,
+            bit,
+        );
 
-                    return count;
-                }
-            }
-        }
+        Counter64::with_init(high.checked_shl(bit).unwrap_or(0) | low)
+    }
+
+    /// Create new counter from the bit pattern of `num`
+    ///
+    /// It's a reinterpretation, not a conversion, e.g. `-1` starts from `u64::MAX`.
+    /// The inverse of [`as_signed`](#method.as_signed).
+    ///
+    /// # Safety
+    ///
+    /// Same as [`with_init`](#method.with_init).
+    pub unsafe fn with_init_signed(num: i64) -> Self {
+        Counter64::with_init(num as u64)
+    }
 
-        /// Increase counter by 1, and return previous value
-        pub fn incr(&self) -> u64 {
-            let prev = self.get();
+    /// Create new independent counter starting from this one's current value
+    ///
+    /// Nothing is shared afterwards, e.g. to branch an ID space.
+    /// Increments of either counter are not visible to the other.
+    pub fn fork(&self) -> Self {
+        // It starts from a value this counter already reached,
+        // so it's as monotonic as the original from the caller's view.
+        unsafe { Counter64::with_init(self.get()) }
+    }
 
-            let _ =
-                self.n1.fetch_add(1, O::Release) == usize::MAX &&
-                self.n2.fetch_add(1, O::Release) == usize::MAX;
+    /// How often operations on this counter had to retry under contention
+    ///
+    /// Spinning is otherwise invisible, so it tells whether a slow counter
+    /// suffers from contention. Only [`get`](#method.get) and the
+    /// compare-and-swap loops are instrumented, each retry costs an extra
+    /// atomic add on the slow path.
+    #[cfg(feature = "diagnostics")]
+    pub fn contention_stats(&self) -> ContentionStats {
+        self.diagnostics.stats()
+    }
 
-            prev
+    /// Get counter's current value
+    ///
+    /// It's always a consistent snapshot. On the multi-word backings
+    /// it spins while concurrent writers are updating the lanes,
+    /// see [`get_approx`](#method.get_approx) for the one which doesn't.
+    /// After [`READ_RETRY_LIMIT`](backing/constant.READ_RETRY_LIMIT.html) retries
+    /// it backs off between them, yielding the thread with the `std` feature.
+    #[inline]
+    pub fn get(&self) -> u64 {
+        #[cfg(feature = "diagnostics")]
+        {
+            let (num, retries) = self.inner.load_with_retries();
+            self.diagnostics.record_read_retries(retries);
+            num
         }
+
+        #[cfg(not(feature = "diagnostics"))]
+        self.inner.load()
     }
-}
 
-#[cfg(target_pointer_width = "16")]
-mod counter {
-    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering as O};
-    use std::usize;
+    /// Get counter's current value split into its `(high, low)` 32bit words
+    ///
+    /// Both come from the same read, e.g. to fill two fields of a protocol header.
+    pub fn split_half(&self) -> (u32, u32) {
+        let num = self.get();
+        ((num >> 32) as u32, num as u32)
+    }
+
+    /// Get counter's current value reinterpreted as `i64`
+    ///
+    /// It's the same bits as [`get`](#method.get), not a saturating conversion,
+    /// so values above `i64::MAX` come out negative. For counters holding
+    /// signed values, e.g. a gauge displayed as a signed delta.
+    pub fn as_signed(&self) -> i64 {
+        self.get() as i64
+    }
+
+    /// Get counter's current value split into its `(high, low)` parts at `bit`
+    ///
+    /// `low` holds the bits below `bit` and `high` the rest shifted down,
+    /// e.g. a tag packed above a sequence number. Generalizes
+    /// [`split_half`](#method.split_half), which splits at 32.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bit > 64`.
+    pub fn split_at_bit(&self, bit: u32) -> (u64, u64) {
+        assert!(bit <= 64, "bit position {} is out of range", bit);
+
+        let num = self.get();
+        (num.checked_shr(bit).unwrap_or(0), low_bits(num, bit))
+    }
 
-    #[derive(Debug)]
-    pub struct Counter {
-        n1: AtomicUsize,
-        n2: AtomicUsize,
-        n3: AtomicUsize,
-        n4: AtomicUsize,
+    /// Number of significant bits of the counter's current value
+    ///
+    /// 0 for 0 and 64 for `u64::MAX`, e.g. to size a variable-length encoding of IDs.
+    pub fn bits(&self) -> u32 {
+        64 - self.get().leading_zeros()
     }
 
-    pub const COUNTER_INIT: Counter = Counter {
-        n1: ATOMIC_USIZE_INIT,
-        n2: ATOMIC_USIZE_INIT,
-        n3: ATOMIC_USIZE_INIT,
-        n4: ATOMIC_USIZE_INIT,
-    };
+    /// Get counter's current value if it's not `last`
+    ///
+    /// For edge-triggered monitoring, pass the value seen last time
+    /// and react only to `Some`. It's a single read, so the counter may
+    /// have changed and come back to `last` in between, which reads as `None`.
+    pub fn observe_change(&self, last: u64) -> Option<u64> {
+        let current = self.get();
 
-    impl Counter {
-        /// Create new counter from 0
-        pub fn new() -> Self {
-            COUNTER_INIT
+        if current != last {
+            Some(current)
+        } else {
+            None
         }
+    }
+
+    /// Busy-wait until counter's value is not `last`, and return it
+    ///
+    /// **It burns the CPU while waiting.** For latency-critical pollers which
+    /// expect a change within about a microsecond and can't afford to sleep.
+    /// It backs off with exponentially more `spin_loop` hints between reads,
+    /// up to 64 of them, but never yields the thread.
+    pub fn spin_wait_change(&self, last: u64) -> u64 {
+        let mut spins = 1;
+
+        loop {
+            if let Some(current) = self.observe_change(last) {
+                return current;
+            }
 
-        pub unsafe fn with_init(num: u64) -> Self {
-            Counter {
-                n1: AtomicUsize::new((num & 0xFFFF) as usize),
-                n2: AtomicUsize::new(((num >> 16) & 0xFFFF) as usize),
-                n3: AtomicUsize::new(((num >> 32) & 0xFFFF) as usize),
-                n4: AtomicUsize::new(((num >> 48) & 0xFFFF) as usize),
+            for _ in 0..spins {
+                core::hint::spin_loop();
             }
+            spins = (spins * 2).min(SPIN_LIMIT);
         }
+    }
+
+    /// Get counter's current value with `Acquire` ordering
+    ///
+    /// Once it observes the value written by [`add_release`](#method.add_release)
+    /// or [`incr_release`](#method.incr_release), everything the writer stored
+    /// before that call is visible to this thread.
+    #[inline]
+    pub fn get_acquire(&self) -> u64 {
+        self.inner.load_acquire()
+    }
 
-        /// Get counter's current value
-        pub fn get(&self) -> u64 {
-            loop {
-                let n1 = self.n1.load(O::SeqCst);
-                let n2 = self.n2.load(O::SeqCst);
-                let n3 = self.n3.load(O::SeqCst);
-                let n4 = self.n4.load(O::SeqCst);
+    /// Get counter's current value with the generation it was read at
+    ///
+    /// Compare generations of two calls to detect any write in between.
+    /// On the multi-word backings it's the number of completed writes,
+    /// taken from the sequence lock of the consistent read.
+    /// Single-word backings have no such number and return the value itself,
+    /// so writes which leave the value unchanged, e.g. `add(0)`, or restore
+    /// an earlier one, e.g. [`swap`](#method.swap), go undetected there.
+    pub fn get_with_generation(&self) -> (u64, u64) {
+        self.inner.load_with_generation()
+    }
 
-                if n1 == self.n1.load(O::SeqCst) {
-                    let mut count = 0u64;
-                    count += n4;
-                    count <<= 16;
-                    count += n3;
-                    count <<= 16;
-                    count += n2;
-                    count <<= 16;
-                    count += n1;
+    /// Get counter's current value, same as [`get`](#method.get)
+    ///
+    /// For the call sites which want to be explicit that they need the precise value.
+    /// Its retries are counted by [`contention_stats`](#method.contention_stats) like `get`'s.
+    #[inline]
+    pub fn get_exact(&self) -> u64 {
+        self.get()
+    }
 
-                    return count;
-                }
-            }
+    /// Get counter's current value without waiting for concurrent writers
+    ///
+    /// On `Atomic64` it's the same as [`get`](#method.get).
+    /// On the multi-word backings it reads the lanes once, so if a write
+    /// is in progress the result may mix lanes from before and after it,
+    /// e.g. the low lane already wrapped while the high lane isn't carried yet.
+    /// Use it only where a fast approximate value is good enough.
+    pub fn get_approx(&self) -> u64 {
+        self.inner.load_approx()
+    }
+
+    /// Get counter's current value, or fail if a writer is in progress
+    ///
+    /// On the multi-word backings it reads the lanes once, like
+    /// [`get_approx`](#method.get_approx), but returns
+    /// `Err(CounterError::InconsistentRead)` instead of a possibly torn value.
+    /// On `Atomic64` it never fails.
+    pub fn try_get(&self) -> Result<u64, CounterError> {
+        self.inner.try_load().ok_or(CounterError::InconsistentRead)
+    }
+
+    /// Get counter's current value, giving up on consistency after `max_spins` retries
+    ///
+    /// For latency-bounded readers on the multi-word backings, between
+    /// [`get`](#method.get) which spins as long as writers interfere and
+    /// [`get_approx`](#method.get_approx) which never retries. Returns `Ok` with
+    /// a consistent value if it got one within `1 + max_spins` attempts, or `Err`
+    /// with an approximate value, which may mix lanes like `get_approx`.
+    /// On `Atomic64` it's always `Ok`.
+    pub fn try_get_consistent_within(&self, max_spins: u32) -> Result<u64, u64> {
+        self.inner.load_within(max_spins)
+    }
+
+    /// Get counter's current value as `u32`
+    ///
+    /// Returns `Err(CounterError::Truncation)` if it's larger than `u32::MAX`.
+    pub fn try_get_u32(&self) -> Result<u32, CounterError> {
+        let num = self.get();
+
+        if num > u32::MAX as u64 {
+            Err(CounterError::Truncation)
+        } else {
+            Ok(num as u32)
+        }
+    }
+
+    /// Check whether the next [`incr`](#method.incr) would wrap around
+    ///
+    /// Handy to rotate to a new epoch before hitting the ceiling.
+    /// It's advisory only, other threads may modify the counter
+    /// between this check and the next increment.
+    pub fn peek_will_wrap(&self) -> bool {
+        self.will_wrap_on_add(1)
+    }
+
+    /// Check whether adding `n` would wrap around
+    ///
+    /// Advisory only, like [`peek_will_wrap`](#method.peek_will_wrap).
+    pub fn will_wrap_on_add(&self, n: u64) -> bool {
+        self.get().checked_add(n).is_none()
+    }
+
+    /// Increase counter by `n`, and return previous value
+    ///
+    /// Panics on overflow in debug builds like the integer arithmetic,
+    /// and wraps around in release builds.
+    /// Use [`wrapping_add`](#method.wrapping_add) if wrapping is intended.
+    #[inline]
+    pub fn add(&self, n: u64) -> u64 {
+        let prev = self.wrapping_add(n);
+        debug_check_overflow(prev, n);
+        prev
+    }
+
+    /// Increase counter by `n`, and return previous value
+    ///
+    /// Always wraps around on overflow, for modular arithmetic.
+    #[inline]
+    pub fn wrapping_add(&self, n: u64) -> u64 {
+        self.inner.fetch_add(n)
+    }
+
+    /// Increase counter by `n` with the least overhead possible
+    ///
+    /// For the hottest loops, after profiling showed that [`add`](#method.add)
+    /// matters. It's always `Relaxed` even with the `seqcst` feature, skips the
+    /// debug overflow check and returns nothing, so on the single-word backing
+    /// it's a bare `fetch_add(n, Relaxed)`. It wraps around on overflow.
+    #[inline]
+    pub fn fetch_add_relaxed_unchecked(&self, n: u64) {
+        self.inner.fetch_add_ordered(n, Ordering::Relaxed);
+    }
+
+    /// Increase counter by `n` with `Release` ordering, and return previous value
+    ///
+    /// Publishes everything this thread stored before it
+    /// to the readers using [`get_acquire`](#method.get_acquire).
+    /// Panics on overflow in debug builds, like [`add`](#method.add).
+    #[inline]
+    pub fn add_release(&self, n: u64) -> u64 {
+        let prev = self.inner.fetch_add_ordered(n, RELEASE);
+        debug_check_overflow(prev, n);
+        prev
+    }
+
+    /// Increase counter by 1 with `Release` ordering, and return previous value
+    ///
+    /// See [`add_release`](#method.add_release).
+    #[inline]
+    pub fn incr_release(&self) -> u64 {
+        self.add_release(1)
+    }
+
+    /// Increase counter by 1 with `order`, and return new value
+    ///
+    /// For advancing the cursor of a ring buffer or a sequencer,
+    /// where the new value is the position to publish. With `Release`
+    /// it pairs with [`get_acquire`](#method.get_acquire) on the consumer side,
+    /// which then sees every slot written before the cursor reached the value it read.
+    ///
+    /// The multi-word backings are always at least `AcqRel` due to their sequence lock.
+    /// Wraps around on overflow.
+    #[inline]
+    pub fn publish(&self, order: Ordering) -> u64 {
+        self.inner.fetch_add_ordered(1, order).wrapping_add(1)
+    }
+
+    /// Set counter to `value`, and return previous value
+    ///
+    /// Note that this can decrease the counter, so it breaks monotonicity.
+    pub fn swap(&self, value: u64) -> u64 {
+        self.inner.swap(value)
+    }
+
+    /// Reset counter to 0 if its value is still `expected`, and return whether it did
+    ///
+    /// For "read the value, process it, then reset only if nothing arrived meanwhile".
+    /// If some increment arrived it fails and keeps the counter, so the caller
+    /// can read and process it again instead of losing those increments.
+    pub fn reset_if_unchanged(&self, expected: u64) -> bool {
+        self.compare_exchange(expected, 0).is_ok()
+    }
+
+    /// Reset counter to 0 and add its value to `dst`, and return the value drained
+    ///
+    /// Flushes a per-shard counter into a global total. The reset is a single
+    /// [`swap`](#method.swap), so increments racing with it are either drained
+    /// now or left for the next drain, never lost. Like `swap` it breaks
+    /// monotonicity of this counter, but not of `dst`.
+    pub fn drain_into(&self, dst: &Self) -> u64 {
+        let value = self.swap(0);
+        dst.add(value);
+        value
+    }
+
+    /// Set counter to `new` if its current value is `current`
+    ///
+    /// Returns `Ok(previous)` if updated, `Err(current)` otherwise.
+    ///
+    /// Note that `new` can be smaller than `current`, which breaks monotonicity.
+    pub fn compare_exchange(&self, current: u64, new: u64) -> Result<u64, u64> {
+        self.inner.compare_exchange(current, new)
+    }
+
+    /// Update counter with `f` unless it returns `None`
+    ///
+    /// Returns `Ok(previous)` if updated, `Err(current)` otherwise.
+    pub(crate) fn fetch_update<F>(&self, f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>
+    {
+        #[cfg(feature = "diagnostics")]
+        let (mut calls, mut f) = (0u32, f);
+        #[cfg(feature = "diagnostics")]
+        let f = |num| {
+            calls += 1;
+            f(num)
+        };
+
+        let res = self.inner.fetch_update(f);
+
+        #[cfg(feature = "diagnostics")]
+        self.diagnostics.record_cas_retries(calls.saturating_sub(1));
+
+        res
+    }
+
+    /// Update counter with `f` unless it returns `None`, built on the weak CAS
+    ///
+    /// Returns `Ok(previous)` if updated, `Err(current)` otherwise.
+    ///
+    /// On LL/SC targets like ARM the weak CAS avoids an inner retry loop,
+    /// which can be faster for tight loops with a cheap `f`. In exchange it may
+    /// fail spuriously, so `f` may be called more often than with a strong CAS,
+    /// even with the same value, and should be cheap and free of side effects.
+    /// The multi-word backings never fail spuriously.
+    ///
+    /// On every backing `f` runs outside of any lock, so it may read the counter,
+    /// and if it panics the counter is left untouched.
+    pub fn fetch_update_weak<F>(&self, f: F) -> Result<u64, u64>
+    where F: FnMut(u64) -> Option<u64>
+    {
+        #[cfg(feature = "diagnostics")]
+        let (mut calls, mut f) = (0u32, f);
+        #[cfg(feature = "diagnostics")]
+        let f = |num| {
+            calls += 1;
+            f(num)
+        };
+
+        let res = self.inner.fetch_update_weak(f);
+
+        #[cfg(feature = "diagnostics")]
+        self.diagnostics.record_cas_retries(calls.saturating_sub(1));
+
+        res
+    }
+
+    /// Set counter to `max(current, value)`, and return previous value
+    pub fn fetch_max(&self, value: u64) -> u64 {
+        self.inner.fetch_max(value)
+    }
+
+    /// Set counter to `max(current, value)`, and return previous value
+    /// along with whether the counter advanced to `value`
+    ///
+    /// Handy for high-water marks, "react only if it's a new record".
+    pub fn replace_max(&self, value: u64) -> (u64, bool) {
+        let prev = self.fetch_max(value);
+        (prev, value > prev)
+    }
+
+    /// Set counter to `max(current, value)`, and return previous value
+    /// along with the number of attempts it took
+    ///
+    /// Same as [`fetch_max`](#method.fetch_max) but always a compare-and-swap loop,
+    /// counting its iterations. It's at least 1, more means other threads modified
+    /// the counter in between, e.g. to diagnose contention on a high-water mark.
+    pub fn fetch_max_counted(&self, value: u64) -> (u64, u32) {
+        let mut attempts = 0u32;
+        let prev = self
+            .fetch_update(|num| {
+                attempts = attempts.saturating_add(1);
+                if value > num { Some(value) } else { None }
+            })
+            .unwrap_or_else(|num| num);
+
+        (prev, attempts)
+    }
+
+    /// Set counter to `max(current, value)`, and return new value
+    ///
+    /// [`fetch_max`](#method.fetch_max) returning where the counter ended up,
+    /// for catching up to a known position, e.g. syncing a replica's offset.
+    pub fn advance_to(&self, value: u64) -> u64 {
+        self.fetch_max(value).max(value)
+    }
+
+    /// Set counter to the max of its and `other`'s current values, and return new value
+    ///
+    /// Aggregates high-water marks across shards, whose total is their max, not their sum.
+    /// Reads `other` once, so later changes to it are not reflected.
+    pub fn merge_max(&self, other: &Self) -> u64 {
+        self.advance_to(other.get())
+    }
+
+    /// Set counter to `value` unless it would decrease, and return previous value
+    ///
+    /// Returns `Err(current)` without storing if `value` is less than it.
+    /// For fast-forwarding the counter, e.g. to an offset persisted before
+    /// a restart, without risking to move it backward.
+    pub fn checked_set_monotonic(&self, value: u64) -> Result<u64, u64> {
+        self.fetch_update(|num| if value >= num { Some(value) } else { None })
+    }
+
+    /// Set counter to the max of its current value and all of `values`, and return new value
+    ///
+    /// Takes the max of `values` first, so it's a single
+    /// [`fetch_max`](#method.fetch_max) regardless of their count.
+    pub fn store_max_from_slice(&self, values: &[u64]) -> u64 {
+        match values.iter().max() {
+            Some(&max) => self.fetch_max(max).max(max),
+            None => self.get(),
         }
+    }
+
+    /// Set counter to `min(current, value)`, and return previous value
+    ///
+    /// Note that this can decrease the counter, so it breaks monotonicity.
+    #[cfg(feature = "gauge")]
+    pub fn fetch_min(&self, value: u64) -> u64 {
+        self.inner.fetch_min(value)
+    }
+
+    /// Decrease counter by `n`, and return previous value
+    ///
+    /// Returns `None` if it would go below 0.
+    /// Note that this decreases the counter, so it breaks monotonicity.
+    #[cfg(feature = "gauge")]
+    pub fn checked_sub(&self, n: u64) -> Option<u64> {
+        self.fetch_update(|num| num.checked_sub(n)).ok()
+    }
+
+    /// Decrease counter by `n`, and return previous value
+    ///
+    /// Stops at 0 instead of wrapping around.
+    /// Note that this decreases the counter, so it breaks monotonicity.
+    #[cfg(feature = "gauge")]
+    pub fn saturating_sub(&self, n: u64) -> u64 {
+        self.fetch_update(|num| Some(num.saturating_sub(n)))
+            .unwrap_or_else(|num| num)
+    }
+
+    /// Divide counter by `factor`, and return new value
+    ///
+    /// For exponential decay of a rate estimate, e.g. halving it periodically.
+    /// Note that this decreases the counter, so it breaks monotonicity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is 0.
+    #[cfg(feature = "gauge")]
+    pub fn scale_down(&self, factor: u64) -> u64 {
+        assert!(factor != 0, "scale_down factor must be nonzero");
+
+        let prev = self.fetch_update(|num| Some(num / factor))
+            .unwrap_or_else(|num| num);
+        prev / factor
+    }
+
+    /// Increase counter by 1, and return previous value
+    ///
+    /// When allocating IDs it's the one the caller owns.
+    /// Panics on overflow in debug builds, like [`add`](#method.add).
+    #[inline]
+    pub fn incr(&self) -> u64 {
+        self.add(1)
+    }
+
+    /// Increase counter by 1, and return new value
+    ///
+    /// When allocating IDs it's the next free one, i.e. the owned ID plus 1.
+    pub fn incr_return_new(&self) -> u64 {
+        self.add_return_new(1)
+    }
+
+    /// Increase counter by 1, and return the next free value, i.e. previous value plus 1
+    ///
+    /// Same as [`incr_return_new`](#method.incr_return_new), named for allocating ranges.
+    /// [`incr`](#method.incr) returns the value the caller owns, this returns where
+    /// the next allocation starts, like the `end` of [`try_reserve`](#method.try_reserve)'s range.
+    /// Panics on overflow in debug builds, like [`add`](#method.add).
+    pub fn incr_returning_next(&self) -> u64 {
+        self.incr_return_new()
+    }
+
+    /// Increase counter by 1 if `cond` holds, and return new value either way
+    ///
+    /// Adds `cond as u64` instead of branching on it, so counting inside
+    /// hot loops doesn't pay for mispredicted branches. It always writes,
+    /// so a false `cond` still costs an atomic add of 0.
+    #[inline]
+    pub fn count_if(&self, cond: bool) -> u64 {
+        self.add_return_new(cond as u64)
+    }
+
+    /// Increase counter by `n`, and return new value
+    ///
+    /// When allocating IDs it's the end of the owned range, i.e. the next free one.
+    /// Panics on overflow in debug builds, like [`add`](#method.add).
+    pub fn add_return_new(&self, n: u64) -> u64 {
+        self.add(n).wrapping_add(n)
+    }
+
+    /// Increase counter by 1, and return previous value
+    ///
+    /// Returns `None` if it would overflow.
+    pub fn checked_incr(&self) -> Option<u64> {
+        self.checked_add(1)
+    }
 
-        /// Increase counter by 1, and return previous value
-        pub fn incr(&self) -> u64 {
-            let prev = self.get();
+    /// Increase counter by 1, and return new value
+    ///
+    /// Returns `None` if it would overflow.
+    pub fn checked_incr_return_new(&self) -> Option<u64> {
+        self.checked_add_return_new(1)
+    }
+
+    /// Increase counter by `n`, and return previous value along with whether it wrapped around
+    ///
+    /// Unlike [`checked_add`](#method.checked_add) it always adds,
+    /// for the callers who continue on overflow but want to log it.
+    pub fn add_detailed(&self, n: u64) -> (u64, bool) {
+        let prev = self.wrapping_add(n);
+        (prev, prev.checked_add(n).is_none())
+    }
+
+    /// Increase counter by `n`, and return both previous and new value
+    ///
+    /// Both come from the single atomic add, e.g. to log "went from X to Y"
+    /// without a racy second read. Always wraps around on overflow like
+    /// [`wrapping_add`](#method.wrapping_add), so new less than previous
+    /// means it wrapped.
+    pub fn fetch_add_returning_both(&self, n: u64) -> (u64, u64) {
+        let prev = self.wrapping_add(n);
+        (prev, prev.wrapping_add(n))
+    }
+
+    /// Increase counter by the sum of `deltas` in a single add, and return previous value
+    ///
+    /// For several deltas computed separately, e.g. the byte counts of each field.
+    /// The sum and the add both wrap around on overflow, so the result is the same
+    /// as [`wrapping_add`](#method.wrapping_add)ing each delta, but atomic.
+    pub fn fetch_add_array(&self, deltas: &[u64]) -> u64 {
+        self.wrapping_add(deltas.iter().fold(0, |sum: u64, &n| sum.wrapping_add(n)))
+    }
+
+    /// Increase counter by `n`, and return new value along with whether it wrapped around
+    ///
+    /// The atomic counterpart of `u64::overflowing_add`, it always adds.
+    /// See [`add_detailed`](#method.add_detailed) for the previous value instead.
+    pub fn overflowing_add(&self, n: u64) -> (u64, bool) {
+        let (prev, wrapped) = self.add_detailed(n);
+        (prev.wrapping_add(n), wrapped)
+    }
+
+    /// Increase counter by `n`, and return previous value
+    ///
+    /// Returns `None` if it would overflow.
+    pub fn checked_add(&self, n: u64) -> Option<u64> {
+        self.fetch_update(|num| num.checked_add(n)).ok()
+    }
+
+    /// Increase counter by `n`, and return new value
+    ///
+    /// Returns `None` if it would overflow.
+    pub fn checked_add_return_new(&self, n: u64) -> Option<u64> {
+        self.checked_add(n).map(|prev| prev + n)
+    }
+
+    /// Increase counter by `n`, and return previous value
+    ///
+    /// Same as [`checked_add`](#method.checked_add),
+    /// but returns `Err(CounterError::Overflow)` if it would overflow.
+    pub fn try_add(&self, n: u64) -> Result<u64, CounterError> {
+        self.checked_add(n).ok_or(CounterError::Overflow)
+    }
+
+    /// Reserve `count` consecutive IDs, unless it would push the counter past `limit`
+    ///
+    /// Returns the reserved IDs, or `None` without reserving anything
+    /// if the counter would end up larger than `limit`. For bounded ID pools,
+    /// where `limit` is one past the last ID of the pool.
+    pub fn try_reserve(&self, count: usize, limit: u64) -> Option<Range<u64>> {
+        self.reserve_range(count, limit).ok()
+    }
+
+    /// Reserve `count` consecutive IDs, unless it would push the counter past `limit`
+    ///
+    /// Same as [`try_reserve`](#method.try_reserve), but on failure returns
+    /// the counter's value it was checked against, so the caller knows
+    /// how much is left in the pool, e.g. to retry with a smaller `count`.
+    pub fn reserve_range(&self, count: usize, limit: u64) -> Result<Range<u64>, u64> {
+        let count = count as u64;
+
+        self.fetch_update(|num| num.checked_add(count).filter(|&end| end <= limit))
+            .map(|prev| prev..prev + count)
+    }
+
+    /// Increase counter by `count` unless it would push the counter past `limit`,
+    /// and return previous value
+    ///
+    /// The returns-previous sibling of [`try_reserve`](#method.try_reserve),
+    /// the result is the start of the reserved IDs.
+    pub fn checked_incr_n(&self, count: usize, limit: u64) -> Option<u64> {
+        self.try_reserve(count, limit).map(|range| range.start)
+    }
+
+    /// Increase counter by `n` if `pred` accepts its current value, and return previous value
+    ///
+    /// Returns `None` without adding if `pred` rejects it. The general form of
+    /// conditional adds, e.g. "only below a cap" or "only when even".
+    /// `pred` may be called several times when other threads modify the counter
+    /// concurrently, so it should be cheap and free of side effects. It runs
+    /// outside of any lock, so if it panics the counter is left untouched.
+    /// Panics on overflow in debug builds, like [`add`](#method.add).
+    pub fn fetch_add_if<F>(&self, n: u64, mut pred: F) -> Option<u64>
+    where F: FnMut(u64) -> bool
+    {
+        let prev = self
+            .fetch_update(|num| if pred(num) { Some(num.wrapping_add(n)) } else { None })
+            .ok()?;
+        debug_check_overflow(prev, n);
+        Some(prev)
+    }
 
-            let _ =
-                self.n1.fetch_add(1, O::Release) == usize::MAX &&
-                self.n2.fetch_add(1, O::Release) == usize::MAX &&
-                self.n3.fetch_add(1, O::Release) == usize::MAX &&
-                self.n4.fetch_add(1, O::Release) == usize::MAX;
+    /// Increase counter by 1 if its current value is `expected`
+    ///
+    /// Returns whether it increased. Handy for optimistic concurrency,
+    /// "move on only if no one else did since I looked".
+    pub fn compare_and_incr(&self, expected: u64) -> bool {
+        self.compare_exchange(expected, expected.wrapping_add(1)).is_ok()
+    }
+
+    /// Increase counter by `n`, and return previous value
+    ///
+    /// Stops at `u64::MAX` instead of wrapping around.
+    pub fn saturating_add(&self, n: u64) -> u64 {
+        self.fetch_update(|num| Some(num.saturating_add(n)))
+            .unwrap_or_else(|num| num)
+    }
+
+    /// Increase counter by `n`, and return new value
+    ///
+    /// The saturating analogue of [`add_return_new`](#method.add_return_new),
+    /// it stops at `u64::MAX` instead of wrapping around.
+    pub fn saturating_add_return_new(&self, n: u64) -> u64 {
+        self.saturating_add(n).saturating_add(n)
+    }
+}
 
-            prev
+/// Assertions for downstream tests
+#[cfg(feature = "test-util")]
+impl<B: Backing> Counter64<B> {
+    /// Assert that counter's current value is `expected`
+    ///
+    /// Panics with both values if it isn't, reported at the caller's location.
+    #[track_caller]
+    pub fn assert_eq_value(&self, expected: u64) {
+        let actual = self.get();
+
+        if actual != expected {
+            panic!("counter value mismatch: expected {}, actual {}", expected, actual);
         }
     }
 }
 
+/// Shorthands for sharing the counter across threads
+#[cfg(feature = "std")]
+impl<B: Backing> Counter64<B> {
+    /// Create new counter from 0, shared behind an `Arc`
+    pub fn arc_new() -> Arc<Self> {
+        Arc::new(Counter64::new())
+    }
+
+    /// Move the counter behind an `Arc` for sharing
+    pub fn into_arc(self) -> Arc<Self> {
+        Arc::new(self)
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+impl Counter64<backing::Atomic64> {
+    /// Raw pointer to the counter's value, for FFI consumers like profilers
+    ///
+    /// It points to the storage of the underlying `AtomicU64`,
+    /// which has the same layout as `u64`. The pointer is valid as long as the counter is.
+    ///
+    /// Every access through it must be atomic, as the counter is concurrently updated.
+    /// Read it with `AtomicU64::from_ptr` or C11 `atomic_load` on `_Atomic uint64_t`,
+    /// never with a plain dereference. Writing through it is not supported.
+    /// Only the single-word backing offers it, lanes have no single `u64` to point to.
+    pub fn as_ptr(&self) -> *const u64 {
+        self.inner.as_ptr()
+    }
+
+    /// Get counter's current value with a plain, non-atomic load
+    ///
+    /// Even a `Relaxed` load keeps the compiler from merging or hoisting it,
+    /// which can matter in single-threaded hot loops. Prefer [`get`](#method.get)
+    /// unless profiling says otherwise.
+    ///
+    /// # Safety
+    ///
+    /// No other thread may write the counter concurrently with this call,
+    /// e.g. it's owned by the current thread or guarded by an external lock.
+    /// Otherwise it's a data race, which is undefined behavior.
+    #[inline]
+    pub unsafe fn load_unsync(&self) -> u64 {
+        *self.inner.as_ptr()
+    }
+}
+
+/// Bits of `num` below `bit`, which is at most 64
+#[inline]
+fn low_bits(num: u64, bit: u32) -> u64 {
+    num & 1u64.checked_shl(bit).map_or(u64::MAX, |end| end - 1)
+}
+
+/// Most `spin_loop` hints between reads of [`Counter64::spin_wait_change`]
+const SPIN_LIMIT: u32 = 64;
+
+/// Panic if adding `n` to `prev` overflowed, only in debug builds
+#[inline]
+fn debug_check_overflow(prev: u64, n: u64) {
+    if cfg!(debug_assertions) && prev.checked_add(n).is_none() {
+        panic!("counter overflowed adding {} to {}", n, prev);
+    }
+}
+
+/// Escape hatches to the bitwise operations of the underlying `AtomicU64`.
+///
+/// **These bypass the counter abstraction entirely.**
+/// Clearing bits decreases the counter, and a counter used for flag-packing
+/// is no longer a meaningful count. Only the single-word backing offers them.
+#[cfg(all(feature = "unsafe_bitops", target_has_atomic = "64"))]
+impl Counter64<backing::Atomic64> {
+    /// Bitwise "and" with `value`, and return previous value
+    pub fn fetch_and(&self, value: u64) -> u64 {
+        self.inner.as_atomic().fetch_and(value, ORDER)
+    }
+
+    /// Bitwise "nand" with `value`, and return previous value
+    pub fn fetch_nand(&self, value: u64) -> u64 {
+        self.inner.as_atomic().fetch_nand(value, ORDER)
+    }
+
+    /// Bitwise "or" with `value`, and return previous value
+    pub fn fetch_or(&self, value: u64) -> u64 {
+        self.inner.as_atomic().fetch_or(value, ORDER)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use backing::{Lanes16, Native};
+    #[cfg(target_has_atomic = "32")]
+    use backing::Lanes32;
+    #[cfg(feature = "portable-atomic")]
+    use backing::Portable64;
+    #[cfg(feature = "spin")]
+    use backing::Locked64;
+    use proptest::prelude::{any, ProptestConfig, Strategy};
+    use proptest::test_runner::TestRunner;
+    use proptest::{collection, option, sample};
     use std::sync::Arc;
     use std::thread;
 
-    #[test]
-    fn test_multithread_incr() {
-        use std::u32;
+    /// Run each generic test against every backing, as `<backing>::<test>`
+    macro_rules! suite {
+        ($($test:ident),* $(,)*) => {
+            mod native {
+                $( #[test] fn $test() { super::$test::<super::Native>() } )*
+            }
 
-        let counter = unsafe { Counter::with_init(u32::MAX as u64 - 80000) };
-        let counter = Arc::new(counter);
+            #[cfg(target_has_atomic = "32")]
+            mod lanes32 {
+                $( #[test] fn $test() { super::$test::<super::Lanes32>() } )*
+            }
+
+            mod lanes16 {
+                $( #[test] fn $test() { super::$test::<super::Lanes16>() } )*
+            }
+
+            #[cfg(feature = "portable-atomic")]
+            mod portable64 {
+                $( #[test] fn $test() { super::$test::<super::Portable64>() } )*
+            }
+
+            #[cfg(feature = "spin")]
+            mod locked64 {
+                $( #[test] fn $test() { super::$test::<super::Locked64>() } )*
+            }
+        };
+    }
+
+    suite!(
+        test_multithread_incr,
+        test_incr_returns_prev,
+        test_swap_consistent_snapshot,
+        test_drain_into,
+        test_multithread_reset_if_unchanged,
+        test_return_prev_and_new,
+        test_wrapping_near_overflow,
+        test_add_overflow_policy,
+        test_fetch_add_relaxed_unchecked,
+        test_checked,
+        test_checked_near_overflow,
+        test_add_detailed_near_overflow,
+        test_overflowing_add_matches_u64,
+        test_fetch_add_returning_both,
+        test_fetch_add_array,
+        test_parity_with_native,
+        test_fetch_max,
+        test_checked_set_monotonic,
+        test_multithread_fetch_max,
+        test_multithread_replace_max,
+        test_multithread_advance_to,
+        test_merge_max,
+        test_multithread_fetch_max_counted,
+        test_store_max_from_slice,
+        test_multithread_add_disjoint_ranges,
+        test_multithread_try_reserve_within_limit,
+        test_multithread_reserve_range_fills_pool,
+        test_multithread_checked_incr_n_reaches_limit,
+        test_get_exact_consistent,
+        test_get_approx_without_writers,
+        test_get_with_generation_advances,
+        test_get_progress_under_writer_heavy_load,
+        test_try_get_consistent_within_under_writers,
+        prop_add_wrapping_sum,
+        prop_checked_add_never_overflows,
+        prop_saturating_add_clamps,
+        test_saturating_add_return_new_near_overflow,
+        prop_fetch_max_is_max,
+        prop_parity_with_atomic_u64,
+        test_compare_exchange,
+        test_multithread_compare_and_incr,
+        test_fetch_add_if_even,
+        test_multithread_fetch_add_if_below_cap,
+        test_fetch_add_if_panicking_pred,
+        test_multithread_fetch_update_weak_clamp,
+        test_fetch_update_weak_panic_and_reentry,
+        test_try_errors,
+        test_will_wrap_near_overflow,
+        test_into_u64,
+        test_release_acquire_message_passing,
+        test_fork_diverges,
+        test_publish_sequenced_handoff,
+        test_next_free_matches_get,
+        test_observe_change,
+        test_spin_wait_change,
+        test_bits,
+        test_debug_in_derived_struct,
+        test_halves_round_trip,
+        test_split_at_bit_round_trip,
+        test_signed_round_trip,
+        test_count_if,
+    );
+
+    #[cfg(feature = "std")]
+    mod arc {
+        use super::*;
+
+        suite!(test_arc_shared_total);
+    }
+
+    #[cfg(feature = "diagnostics")]
+    mod diagnostics {
+        use super::*;
+
+        suite!(test_contention_stats);
+    }
+
+    #[cfg(feature = "test-util")]
+    mod test_util {
+        use super::*;
+
+        suite!(test_assert_eq_value);
+    }
+
+    #[cfg(feature = "rand")]
+    mod rand {
+        use super::*;
+
+        suite!(test_new_random_start);
+    }
+
+    #[cfg(feature = "gauge")]
+    mod gauge {
+        use super::*;
+
+        suite!(
+            test_fetch_min,
+            test_multithread_fetch_min,
+            test_scale_down,
+            test_scale_down_zero_factor,
+            test_checked_saturating_sub,
+            test_multithread_saturating_sub,
+        );
+    }
+
+    /// Xorshift64, good enough for shuffling test inputs
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    /// Random `u64` biased toward the lane and overflow boundaries
+    fn arbitrary_u64() -> impl Strategy<Value = u64> {
+        const EDGES: [u64; 6] = [0, 1, u32::MAX as u64, 1 << 32, u64::MAX - 1, u64::MAX];
+
+        prop_oneof![
+            sample::select(&EDGES[..]),
+            0..1u64 << 16,
+            (sample::select(&EDGES[..]), 0..16u64).prop_map(|(edge, n)| edge.wrapping_add(n)),
+            any::<u64>(),
+        ]
+    }
+
+    /// Check `prop` with arbitrary starting values and sequences of operands
+    ///
+    /// Failures are shrunk to a minimal case, and persisted under `proptest-regressions`
+    /// to be replayed first by the next runs.
+    fn check_prop<T, F>(ops: impl Strategy<Value = T>, prop: F)
+    where
+        T: fmt::Debug,
+        F: Fn(u64, &[T]),
+    {
+        let config = ProptestConfig {
+            source_file: Some(file!()),
+            ..ProptestConfig::default()
+        };
+        let strategy = (arbitrary_u64(), collection::vec(ops, 0..64));
+
+        TestRunner::new(config)
+            .run(&strategy, |(start, ops)| {
+                prop(start, &ops);
+                Ok(())
+            })
+            .unwrap();
+    }
 
+    fn test_multithread_incr<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64 - 80000) };
+        let counter = Arc::new(counter);
         let handles: Vec<_> = (0..8)
             .map(|_| {
                 let counter = counter.clone();
@@ -204,15 +1430,1579 @@ mod tests {
 
     }
 
-    #[test]
-    fn test_incr_returns_prev() {
-        let mut prev = 0;
-        let counter = Counter::new();
+    fn test_incr_returns_prev<B: Backing>() {
+        let counter = Counter64::<B>::new();
 
-        for _ in 0..80000 {
-            let curr = counter.incr();
-            assert_eq!(curr, prev);
-            prev += 1;
+        for prev in 0..80000 {
+            assert_eq!(counter.incr(), prev);
         }
     }
+
+    fn test_swap_consistent_snapshot<B: Backing>() {
+        const A: u64 = 0x0000_0001_FFFF_FFFF;
+        const B: u64 = 0xFFFF_FFFF_0000_0001;
+
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(A) });
+
+        let swapper = {
+            let counter = counter.clone();
+            thread::spawn(move|| {
+                for i in 0..100000 {
+                    let (next, prev) = if i % 2 == 0 { (B, A) } else { (A, B) };
+                    assert_eq!(counter.swap(next), prev);
+                }
+            })
+        };
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    for _ in 0..100000 {
+                        let value = counter.get();
+                        assert!(value == A || value == B, "torn read: {:x}", value);
+                    }
+                })
+            })
+            .collect();
+
+        swapper.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        assert_eq!(counter.get(), A);
+    }
+
+    fn test_multithread_reset_if_unchanged<B: Backing>() {
+        let counter = Arc::new(Counter64::<B>::new());
+
+        let producers: Vec<_> = (0..2)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    for _ in 0..50000 {
+                        counter.incr();
+                    }
+                })
+            })
+            .collect();
+
+        let (mut flushed, mut failed) = (0, 0);
+        while producers.iter().any(|p| !p.is_finished()) {
+            let value = counter.get();
+            thread::yield_now();
+
+            if counter.reset_if_unchanged(value) {
+                flushed += value;
+            } else {
+                failed += 1;
+            }
+        }
+
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        let value = counter.get();
+        assert!(counter.reset_if_unchanged(value));
+        assert!(!counter.reset_if_unchanged(value + 1));
+        assert_eq!(flushed + value, 100000, "lost counts with {} failed resets", failed);
+    }
+
+    fn test_drain_into<B: Backing>() {
+        let shard = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+        let total = Counter64::<B>::new();
+
+        assert_eq!(shard.drain_into(&total), u32::MAX as u64);
+        shard.add(5);
+        assert_eq!(shard.drain_into(&total), 5);
+        assert_eq!(shard.drain_into(&total), 0);
+        assert_eq!((shard.get(), total.get()), (0, u32::MAX as u64 + 5));
+    }
+
+    fn test_return_prev_and_new<B: Backing>() {
+        let counter = Counter64::<B>::new();
+
+        assert_eq!(counter.incr(), 0);
+        assert_eq!(counter.incr_return_new(), 2);
+        assert_eq!(counter.add(10), 2);
+        assert_eq!(counter.add_return_new(10), 22);
+        assert_eq!(counter.get(), 22);
+    }
+
+    fn test_wrapping_near_overflow<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u64::MAX - 1) };
+
+        assert_eq!(counter.wrapping_add(1), u64::MAX - 1);
+        assert_eq!(counter.wrapping_add(1), u64::MAX);
+        assert_eq!(counter.get(), 0);
+        assert_eq!(counter.wrapping_add(u64::MAX), 0);
+        assert_eq!(counter.wrapping_add(3), u64::MAX);
+        assert_eq!(counter.get(), 2);
+    }
+
+    fn test_fetch_add_relaxed_unchecked<B: Backing>() {
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(u32::MAX as u64 - 1000) });
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    for _ in 0..1000 {
+                        counter.fetch_add_relaxed_unchecked(1);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(counter.get(), u32::MAX as u64 + 3000);
+
+        counter.fetch_add_relaxed_unchecked(u64::MAX);
+        assert_eq!(counter.get(), u32::MAX as u64 + 2999);
+    }
+
+    fn test_add_overflow_policy<B: Backing>() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let overflows: [fn(&Counter64<B>) -> u64; 4] = [
+            |c| c.incr(),
+            |c| c.add(2),
+            |c| c.add_return_new(u64::MAX),
+            |c| c.add_release(1),
+        ];
+
+        for overflow in &overflows {
+            let counter = unsafe { Counter64::<B>::with_init(u64::MAX) };
+            let res = panic::catch_unwind(AssertUnwindSafe(|| overflow(&counter)));
+
+            // Wrapped either way, the panic comes after the add
+            assert_ne!(counter.get(), u64::MAX);
+            assert_eq!(res.is_err(), cfg!(debug_assertions));
+        }
+
+        let counter = unsafe { Counter64::<B>::with_init(u64::MAX - 1) };
+        assert_eq!(counter.incr(), u64::MAX - 1);
+        assert_eq!(counter.add(0), u64::MAX);
+    }
+
+    fn test_checked<B: Backing>() {
+        let counter = Counter64::<B>::new();
+
+        assert_eq!(counter.checked_incr(), Some(0));
+        assert_eq!(counter.checked_incr_return_new(), Some(2));
+        assert_eq!(counter.checked_add(10), Some(2));
+        assert_eq!(counter.checked_add_return_new(10), Some(22));
+        assert_eq!(counter.get(), 22);
+    }
+
+    fn test_checked_near_overflow<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u64::MAX - 2) };
+
+        assert_eq!(counter.checked_incr(), Some(u64::MAX - 2));
+        assert_eq!(counter.checked_incr_return_new(), Some(u64::MAX));
+        assert_eq!(counter.checked_incr(), None);
+        assert_eq!(counter.checked_incr_return_new(), None);
+        assert_eq!(counter.checked_add(1), None);
+        assert_eq!(counter.checked_add_return_new(1), None);
+        assert_eq!(counter.checked_add(0), Some(u64::MAX));
+        assert_eq!(counter.checked_add_return_new(0), Some(u64::MAX));
+        assert_eq!(counter.get(), u64::MAX);
+    }
+
+    fn test_add_detailed_near_overflow<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u64::MAX - 2) };
+
+        assert_eq!(counter.add_detailed(1), (u64::MAX - 2, false));
+        assert_eq!(counter.add_detailed(1), (u64::MAX - 1, false));
+        assert_eq!(counter.add_detailed(0), (u64::MAX, false));
+        assert_eq!(counter.add_detailed(1), (u64::MAX, true));
+        assert_eq!(counter.add_detailed(u32::MAX as u64), (0, false));
+        assert_eq!(counter.add_detailed(u64::MAX), (u32::MAX as u64, true));
+        assert_eq!(counter.get(), u32::MAX as u64 - 1);
+    }
+
+    fn test_overflowing_add_matches_u64<B: Backing>() {
+        let inputs = [0, 1, u32::MAX as u64, u32::MAX as u64 + 1, u64::MAX - 1, u64::MAX];
+
+        for &init in &inputs {
+            for &n in &inputs {
+                let counter = unsafe { Counter64::<B>::with_init(init) };
+                assert_eq!(counter.overflowing_add(n), init.overflowing_add(n), "{} + {}", init, n);
+                assert_eq!(counter.get(), init.wrapping_add(n));
+            }
+        }
+    }
+
+    fn test_fetch_add_returning_both<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+
+        assert_eq!(counter.fetch_add_returning_both(1), (u32::MAX as u64, 1 << 32));
+        assert_eq!(counter.fetch_add_returning_both(0), (1 << 32, 1 << 32));
+        assert_eq!(counter.fetch_add_returning_both(u64::MAX - (1 << 32)), (1 << 32, u64::MAX));
+        assert_eq!(counter.fetch_add_returning_both(3), (u64::MAX, 2));
+        assert_eq!(counter.get(), 2);
+    }
+
+    fn test_fetch_add_array<B: Backing>() {
+        let counter = Counter64::<B>::new();
+
+        assert_eq!(counter.fetch_add_array(&[3, 20, 100, u32::MAX as u64]), 0);
+        assert_eq!(counter.get(), 123 + u32::MAX as u64);
+        assert_eq!(counter.fetch_add_array(&[]), 123 + u32::MAX as u64);
+
+        // The pre-sum wraps, while the total fits
+        assert_eq!(counter.fetch_add_array(&[u64::MAX, u64::MAX, 2]), 123 + u32::MAX as u64);
+        assert_eq!(counter.get(), 123 + u32::MAX as u64);
+
+        assert_eq!(counter.fetch_add_array(&[u64::MAX - 123 - u32::MAX as u64, 5]), 123 + u32::MAX as u64);
+        assert_eq!(counter.get(), 4);
+    }
+
+    fn test_parity_with_native<B: Backing>() {
+        let values = [0, 1, u32::MAX as u64, 1 << 32, u64::MAX - 1, u64::MAX];
+
+        for &init in &values {
+            let native = unsafe { Counter::with_init(init) };
+            let counter = unsafe { Counter64::<B>::with_init(init) };
+            assert_eq!(counter.get(), native.get());
+
+            for &n in &values {
+                assert_eq!(counter.wrapping_add(n), native.wrapping_add(n));
+                assert_eq!(counter.checked_add(n), native.checked_add(n));
+                assert_eq!(counter.wrapping_add(1), native.wrapping_add(1));
+                assert_eq!(counter.swap(n ^ init), native.swap(n ^ init));
+                assert_eq!(counter.get(), native.get());
+            }
+        }
+    }
+
+    fn test_checked_set_monotonic<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+
+        assert_eq!(counter.checked_set_monotonic(u32::MAX as u64 + 10), Ok(u32::MAX as u64));
+        assert_eq!(counter.checked_set_monotonic(u32::MAX as u64 + 10), Ok(u32::MAX as u64 + 10));
+        assert_eq!(counter.checked_set_monotonic(u32::MAX as u64), Err(u32::MAX as u64 + 10));
+        assert_eq!(counter.checked_set_monotonic(0), Err(u32::MAX as u64 + 10));
+        assert_eq!(counter.get(), u32::MAX as u64 + 10);
+
+        assert_eq!(counter.checked_set_monotonic(u64::MAX), Ok(u32::MAX as u64 + 10));
+        assert_eq!(counter.get(), u64::MAX);
+    }
+
+    fn test_fetch_max<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(10) };
+
+        assert_eq!(counter.fetch_max(5), 10);
+        assert_eq!(counter.get(), 10);
+        assert_eq!(counter.fetch_max(1 << 40), 10);
+        assert_eq!(counter.get(), 1 << 40);
+    }
+
+    fn test_multithread_fetch_max<B: Backing>() {
+        let counter = Arc::new(Counter64::<B>::new());
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    let mut state = i + 1;
+                    let mut max = 0;
+
+                    for _ in 0..10000 {
+                        let value = xorshift(&mut state);
+                        max = max.max(value);
+                        counter.fetch_max(value);
+                    }
+
+                    max
+                })
+            })
+            .collect();
+
+        let max = handles.into_iter().map(|h| h.join().unwrap()).max().unwrap();
+        assert_eq!(counter.get(), max);
+    }
+
+    fn test_multithread_fetch_max_counted<B: Backing>() {
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(u32::MAX as u64) });
+        assert_eq!(counter.fetch_max_counted(0), (u32::MAX as u64, 1));
+
+        let handles: Vec<_> = (0..4u64)
+            .map(|i| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    let mut attempts = 0;
+                    for j in 0..5000u64 {
+                        let value = u32::MAX as u64 + j * 4 + i;
+                        let (prev, n) = counter.fetch_max_counted(value);
+                        assert!(n >= 1);
+                        assert!(counter.get() >= value.max(prev));
+                        attempts += n as u64;
+                    }
+                    attempts
+                })
+            })
+            .collect();
+
+        let attempts: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+        assert!(attempts >= 20000);
+        assert_eq!(counter.get(), u32::MAX as u64 + 19999);
+    }
+
+    fn test_merge_max<B: Backing>() {
+        let shards: Vec<Counter64<B>> = [7, u32::MAX as u64 + 3, 0, u32::MAX as u64]
+            .iter()
+            .map(|&num| unsafe { Counter64::with_init(num) })
+            .collect();
+        let total = Counter64::<B>::new();
+
+        let merged: Vec<u64> = shards.iter().map(|shard| total.merge_max(shard)).collect();
+        assert_eq!(merged, [7, u32::MAX as u64 + 3, u32::MAX as u64 + 3, u32::MAX as u64 + 3]);
+        assert_eq!(total.get(), u32::MAX as u64 + 3);
+        assert_eq!(shards[2].get(), 0);
+    }
+
+    fn test_multithread_advance_to<B: Backing>() {
+        let base = u32::MAX as u64 - 500;
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(base) });
+
+        let handles: Vec<_> = (0..4u64)
+            .map(|i| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    // Offsets of every thread interleave and arrive out of order
+                    for j in (0..250u64).rev().chain(0..250) {
+                        let value = base + j * 4 + i;
+                        assert!(counter.advance_to(value) >= value);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(counter.get(), base + 999);
+        assert_eq!(counter.advance_to(0), base + 999);
+    }
+
+    fn test_store_max_from_slice<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(100) };
+
+        assert_eq!(counter.store_max_from_slice(&[3, 99, 0]), 100);
+        assert_eq!(counter.get(), 100);
+        assert_eq!(counter.store_max_from_slice(&[3, 1 << 40, 99, u32::MAX as u64]), 1 << 40);
+        assert_eq!(counter.get(), 1 << 40);
+        assert_eq!(counter.store_max_from_slice(&[]), 1 << 40);
+        assert_eq!(counter.store_max_from_slice(&[1 << 40]), 1 << 40);
+    }
+
+    fn test_multithread_replace_max<B: Backing>() {
+        let counter = Arc::new(Counter64::<B>::new());
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    let mut advances = vec![];
+
+                    for j in 1..=2000 {
+                        let value = j * 8 + i;
+                        let (prev, advanced) = counter.replace_max(value);
+                        assert_eq!(advanced, value > prev);
+                        if advanced {
+                            advances.push((prev, value));
+                        }
+                    }
+
+                    for j in (0..=2000).rev() {
+                        let (prev, advanced) = counter.replace_max(j * 8 + i);
+                        assert!(!advanced, "advanced from {} while descending", prev);
+                    }
+
+                    advances
+                })
+            })
+            .collect();
+
+        let mut advances: Vec<_> = handles.into_iter().flat_map(|h| h.join().unwrap()).collect();
+        advances.sort();
+
+        // Every advance starts where the previous one ended
+        let mut current = 0;
+        for (prev, value) in advances {
+            assert_eq!(prev, current);
+            current = value;
+        }
+        assert_eq!(counter.get(), current);
+        assert_eq!(current, 2000 * 8 + 7);
+    }
+
+    #[cfg(feature = "gauge")]
+    fn test_fetch_min<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(10) };
+
+        assert_eq!(counter.fetch_min(20), 10);
+        assert_eq!(counter.get(), 10);
+        assert_eq!(counter.fetch_min(3), 10);
+        assert_eq!(counter.get(), 3);
+    }
+
+    #[cfg(feature = "gauge")]
+    fn test_multithread_fetch_min<B: Backing>() {
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(u64::MAX) });
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    let mut state = i + 1;
+                    let mut min = u64::MAX;
+
+                    for _ in 0..10000 {
+                        let value = xorshift(&mut state);
+                        min = min.min(value);
+                        counter.fetch_min(value);
+                    }
+
+                    min
+                })
+            })
+            .collect();
+
+        let min = handles.into_iter().map(|h| h.join().unwrap()).min().unwrap();
+        assert_eq!(counter.get(), min);
+    }
+
+    #[cfg(feature = "gauge")]
+    fn test_checked_saturating_sub<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(1 << 32) };
+
+        assert_eq!(counter.checked_sub(1), Some(1 << 32));
+        assert_eq!(counter.get(), u32::MAX as u64);
+        assert_eq!(counter.checked_sub(1 << 32), None);
+        assert_eq!(counter.get(), u32::MAX as u64);
+        assert_eq!(counter.checked_sub(u32::MAX as u64), Some(u32::MAX as u64));
+        assert_eq!(counter.checked_sub(0), Some(0));
+
+        counter.add(10);
+        assert_eq!(counter.saturating_sub(3), 10);
+        assert_eq!(counter.saturating_sub(u64::MAX), 7);
+        assert_eq!(counter.saturating_sub(1), 0);
+        assert_eq!(counter.get(), 0);
+    }
+
+    #[cfg(feature = "gauge")]
+    fn test_multithread_saturating_sub<B: Backing>() {
+        let counter = Arc::new(Counter64::<B>::new());
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    for j in 0..10000 {
+                        if i % 2 == 0 {
+                            counter.add(j % 3);
+                        } else {
+                            counter.saturating_sub(j % 5);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        // Adders add at most 4 * 10000 * 2 in total, anything above is a wrap
+        while !handles.iter().all(|h| h.is_finished()) {
+            let value = counter.get();
+            assert!(value <= 80000, "wrapped below 0: {}", value);
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(counter.get() <= 80000);
+    }
+
+    #[cfg(feature = "gauge")]
+    fn test_scale_down<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(1 << 40) };
+
+        for i in 1..=40 {
+            assert_eq!(counter.scale_down(2), 1 << (40 - i));
+        }
+        assert_eq!(counter.scale_down(2), 0);
+        assert_eq!(counter.scale_down(2), 0);
+
+        counter.add(1000);
+        assert_eq!(counter.scale_down(3), 333);
+        assert_eq!(counter.scale_down(1), 333);
+        assert_eq!(counter.scale_down(u64::MAX), 0);
+    }
+
+    #[cfg(feature = "gauge")]
+    fn test_scale_down_zero_factor<B: Backing>() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let counter = unsafe { Counter64::<B>::with_init(42) };
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| counter.scale_down(0)));
+        assert!(res.is_err());
+        assert_eq!(counter.get(), 42);
+    }
+
+    #[cfg(all(feature = "unsafe_bitops", target_has_atomic = "64"))]
+    #[test]
+    fn test_bitops_match_atomic() {
+        use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+        let counter = unsafe { Counter::with_init(0b1100) };
+        let atomic = AtomicU64::new(0b1100);
+        let ops = [0b1010, 0b0110, u64::MAX, 0, 1 << 63];
+
+        for &value in &ops {
+            assert_eq!(counter.fetch_and(value), atomic.fetch_and(value, Relaxed));
+            assert_eq!(counter.fetch_or(value), atomic.fetch_or(value, Relaxed));
+            assert_eq!(counter.fetch_nand(value), atomic.fetch_nand(value, Relaxed));
+            assert_eq!(counter.get(), atomic.load(Relaxed));
+        }
+    }
+
+    #[cfg(target_has_atomic = "64")]
+    #[test]
+    fn test_atomic_u64_round_trip() {
+        use std::sync::atomic::Ordering::Relaxed;
+
+        for &num in &[0, 1, u32::MAX as u64 + 1, u64::MAX] {
+            let counter = Counter::from(AtomicU64::new(num));
+            assert_eq!(counter.get(), num);
+
+            let atomic: AtomicU64 = counter.into();
+            assert_eq!(atomic.load(Relaxed), num);
+        }
+
+        let counter: Counter = AtomicU64::new(41).into();
+        counter.incr();
+        assert_eq!(AtomicU64::from(counter).into_inner(), 42);
+    }
+
+    #[cfg(target_has_atomic = "64")]
+    #[test]
+    fn test_load_unsync_single_thread() {
+        let counter = unsafe { Counter::with_init(u32::MAX as u64) };
+        assert_eq!(unsafe { counter.load_unsync() }, u32::MAX as u64);
+
+        let mut sum = 0;
+        for _ in 0..1000 {
+            counter.incr();
+            sum += unsafe { counter.load_unsync() } - u32::MAX as u64;
+        }
+
+        assert_eq!(sum, 1000 * 1001 / 2);
+        assert_eq!(unsafe { counter.load_unsync() }, counter.get());
+    }
+
+    #[cfg(target_has_atomic = "64")]
+    #[test]
+    fn test_as_ptr_matches_get() {
+        use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+        let counter = Arc::new(unsafe { Counter::with_init(u32::MAX as u64) });
+        let ptr = counter.as_ptr();
+        let atomic = unsafe { AtomicU64::from_ptr(ptr as *mut u64) };
+        assert_eq!(atomic.load(Relaxed), counter.get());
+
+        let writer = {
+            let counter = counter.clone();
+            thread::spawn(move|| {
+                for _ in 0..10000 {
+                    counter.incr();
+                }
+            })
+        };
+
+        let mut prev = 0;
+        while !writer.is_finished() {
+            let value = atomic.load(Relaxed);
+            assert!(value >= prev, "went backward: {} -> {}", prev, value);
+            prev = value;
+        }
+
+        writer.join().unwrap();
+        assert_eq!(counter.as_ptr(), ptr);
+        assert_eq!(atomic.load(Relaxed), counter.get());
+        assert_eq!(counter.get(), u32::MAX as u64 + 10000);
+    }
+
+    fn test_multithread_add_disjoint_ranges<B: Backing>() {
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(u32::MAX as u64 - 5000) });
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    (0..2000)
+                        .map(|j| {
+                            let n = (i + j) % 7 + 1;
+                            (counter.add(n), n)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut ranges: Vec<_> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        ranges.sort();
+
+        let mut next = u32::MAX as u64 - 5000;
+        for (start, n) in ranges {
+            assert_eq!(start, next, "overlapping or missing range");
+            next = start + n;
+        }
+        assert_eq!(counter.get(), next);
+    }
+
+    fn test_multithread_try_reserve_within_limit<B: Backing>() {
+        let start = u32::MAX as u64 - 50;
+        let limit = start + 100;
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(start) });
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    let mut ranges = vec![];
+                    while let Some(range) = counter.try_reserve(i + 1, limit) {
+                        ranges.push(range);
+                    }
+                    ranges
+                })
+            })
+            .collect();
+
+        let mut ranges: Vec<_> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+
+        for pair in ranges.windows(2) {
+            assert!(pair[0].end <= pair[1].start);
+        }
+        assert!(ranges.iter().all(|range| start <= range.start && range.end <= limit));
+
+        // Reservations are contiguous, the slack is less than the largest block
+        let end = counter.get();
+        assert_eq!(ranges.last().map(|range| range.end), Some(end));
+        assert!(limit - end < 4);
+        assert_eq!(counter.try_reserve(0, limit), Some(end..end));
+        assert_eq!(counter.try_reserve(1, u64::MAX), Some(end..end + 1));
+        assert_eq!(counter.try_reserve(1, end), None);
+    }
+
+    fn test_multithread_reserve_range_fills_pool<B: Backing>() {
+        let start = u32::MAX as u64 - 1000;
+        let limit = start + 3001;
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(start) });
+
+        let handles: Vec<_> = [1, 2, 5, 8]
+            .iter()
+            .map(|&count| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    let mut ranges = vec![];
+                    loop {
+                        match counter.reserve_range(count, limit) {
+                            Ok(range) => ranges.push(range),
+                            Err(current) => {
+                                assert!(current <= limit && limit - current < count as u64);
+                                return ranges;
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut ranges: Vec<_> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+
+        // The single ID reserver only gives up once the pool is exhausted
+        assert_eq!(ranges.first().map(|range| range.start), Some(start));
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert_eq!(ranges.last().map(|range| range.end), Some(limit));
+
+        assert_eq!(counter.reserve_range(1, limit), Err(limit));
+        assert_eq!(counter.reserve_range(0, limit), Ok(limit..limit));
+        assert_eq!(counter.reserve_range(1, u64::MAX), Ok(limit..limit + 1));
+        assert_eq!(counter.get(), limit + 1);
+    }
+
+    fn test_multithread_checked_incr_n_reaches_limit<B: Backing>() {
+        const LIMIT: u64 = 4000;
+        let counter = Arc::new(Counter64::<B>::new());
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    let mut starts = vec![];
+                    while let Some(start) = counter.checked_incr_n(4, LIMIT) {
+                        starts.push(start);
+                    }
+                    starts
+                })
+            })
+            .collect();
+
+        let mut starts: Vec<u64> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        starts.sort_unstable();
+
+        assert_eq!(starts.len() as u64 * 4, LIMIT);
+        assert!(starts.iter().enumerate().all(|(i, &start)| start == i as u64 * 4));
+        assert_eq!(counter.get(), LIMIT);
+        assert_eq!(counter.checked_incr_n(1, LIMIT), None);
+    }
+
+    fn test_get_exact_consistent<B: Backing>() {
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(u32::MAX as u64 - 50000) });
+
+        let writer = {
+            let counter = counter.clone();
+            thread::spawn(move|| {
+                for _ in 0..100000 {
+                    counter.incr();
+                }
+            })
+        };
+
+        let mut prev = 0;
+        while !writer.is_finished() {
+            let value = counter.get_exact();
+            assert!(value >= prev, "went backward: {} -> {}", prev, value);
+            assert!(value <= u32::MAX as u64 + 50000, "torn read: {}", value);
+            prev = value;
+        }
+
+        writer.join().unwrap();
+        assert_eq!(counter.get_exact(), u32::MAX as u64 + 50000);
+    }
+
+    fn test_get_approx_without_writers<B: Backing>() {
+        for &init in &[0, u32::MAX as u64, u32::MAX as u64 + 1, u64::MAX] {
+            let counter = unsafe { Counter64::<B>::with_init(init) };
+            assert_eq!(counter.get_approx(), counter.get());
+
+            counter.wrapping_add(1);
+            assert_eq!(counter.get_approx(), init.wrapping_add(1));
+        }
+    }
+
+    fn test_get_with_generation_advances<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+
+        let (value, generation) = counter.get_with_generation();
+        assert_eq!(value, u32::MAX as u64);
+        assert_eq!(counter.get_with_generation(), (value, generation));
+
+        counter.incr();
+        let (value, next) = counter.get_with_generation();
+        assert_eq!(value, u32::MAX as u64 + 1);
+        assert_ne!(next, generation);
+    }
+
+    fn test_try_get_consistent_within_under_writers<B: Backing>() {
+        // No carries out of the lowest 16bit lane, so even torn reads stay in range
+        let start = (5 << 32) + 0x10;
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(start) });
+
+        let writers: Vec<_> = (0..4)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    for _ in 0..10000 {
+                        counter.incr();
+                    }
+                })
+            })
+            .collect();
+
+        let (mut consistent, mut approx) = (0, 0);
+        let mut last = start;
+        while writers.iter().any(|w| !w.is_finished()) {
+            match counter.try_get_consistent_within(2) {
+                Ok(value) => {
+                    assert!(last <= value && value <= start + 40000, "{}", value);
+                    last = value;
+                    consistent += 1;
+                }
+                Err(value) => {
+                    assert!(start <= value && value <= start + 40000, "{}", value);
+                    approx += 1;
+                }
+            }
+        }
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert!(consistent + approx > 0);
+        assert_eq!(counter.try_get_consistent_within(0), Ok(start + 40000));
+    }
+
+    fn test_get_progress_under_writer_heavy_load<B: Backing>() {
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(u32::MAX as u64 - 100000) });
+
+        let writers: Vec<_> = (0..8)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    for _ in 0..25000 {
+                        counter.incr();
+                    }
+                })
+            })
+            .collect();
+
+        let mut prev = 0;
+        let mut reads = 0;
+        while !writers.iter().all(|w| w.is_finished()) {
+            let value = counter.get();
+            assert!(value >= prev, "went backward: {} -> {}", prev, value);
+            assert!(value <= u32::MAX as u64 + 100000, "torn read: {}", value);
+            prev = value;
+            reads += 1;
+        }
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert!(reads > 0);
+        assert_eq!(counter.get(), u32::MAX as u64 + 100000);
+    }
+
+    fn prop_add_wrapping_sum<B: Backing>() {
+        check_prop(arbitrary_u64(), |start, ops| {
+            let counter = unsafe { Counter64::<B>::with_init(start) };
+            let mut expected = start;
+
+            for &n in ops {
+                assert_eq!(counter.wrapping_add(n), expected);
+                expected = expected.wrapping_add(n);
+            }
+
+            assert_eq!(counter.get(), expected);
+        });
+    }
+
+    fn prop_checked_add_never_overflows<B: Backing>() {
+        check_prop(arbitrary_u64(), |start, ops| {
+            let counter = unsafe { Counter64::<B>::with_init(start) };
+
+            for &n in ops {
+                let prev = counter.get();
+
+                match counter.checked_add(n) {
+                    Some(num) => {
+                        assert_eq!(num, prev);
+                        assert_eq!(counter.get(), prev + n);
+                    }
+                    None => {
+                        assert!(prev.checked_add(n).is_none());
+                        assert_eq!(counter.get(), prev);
+                    }
+                }
+                assert!(counter.get() >= start);
+            }
+        });
+    }
+
+    fn prop_saturating_add_clamps<B: Backing>() {
+        check_prop(arbitrary_u64(), |start, ops| {
+            let counter = unsafe { Counter64::<B>::with_init(start) };
+            let mut expected = start;
+
+            for &n in ops {
+                assert_eq!(counter.saturating_add(n), expected);
+                expected = expected.saturating_add(n);
+            }
+
+            assert_eq!(counter.get(), expected);
+        });
+    }
+
+    fn test_saturating_add_return_new_near_overflow<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u64::MAX - 3) };
+
+        assert_eq!(counter.saturating_add_return_new(2), u64::MAX - 1);
+        assert_eq!(counter.saturating_add_return_new(2), u64::MAX);
+        assert_eq!(counter.get(), u64::MAX);
+        assert_eq!(counter.saturating_add_return_new(1), u64::MAX);
+        assert_eq!(counter.saturating_add_return_new(u64::MAX), u64::MAX);
+        assert_eq!(counter.saturating_add_return_new(0), u64::MAX);
+        assert_eq!(counter.get(), u64::MAX);
+    }
+
+    fn prop_fetch_max_is_max<B: Backing>() {
+        check_prop(arbitrary_u64(), |start, ops| {
+            let counter = unsafe { Counter64::<B>::with_init(start) };
+            let mut expected = start;
+
+            for &n in ops {
+                assert_eq!(counter.fetch_max(n), expected);
+                expected = expected.max(n);
+            }
+
+            assert_eq!(counter.get(), expected);
+        });
+    }
+
+    fn prop_parity_with_atomic_u64<B: Backing>() {
+        use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+        #[derive(Debug, Clone, Copy)]
+        enum Op {
+            Add(u64),
+            Swap(u64),
+            CompareExchange(Option<u64>, u64),
+            FetchMax(u64),
+        }
+
+        let ops = prop_oneof![
+            arbitrary_u64().prop_map(Op::Add),
+            arbitrary_u64().prop_map(Op::Swap),
+            // `None` expects the current value, so the exchange mostly succeeds
+            (option::of(arbitrary_u64()), arbitrary_u64())
+                .prop_map(|(current, new)| Op::CompareExchange(current, new)),
+            arbitrary_u64().prop_map(Op::FetchMax),
+        ];
+
+        check_prop(ops, |start, ops| {
+            let counter = unsafe { Counter64::<B>::with_init(start) };
+            let atomic = AtomicU64::new(start);
+
+            for &op in ops {
+                match op {
+                    Op::Add(n) => assert_eq!(counter.wrapping_add(n), atomic.fetch_add(n, Relaxed)),
+                    Op::Swap(n) => assert_eq!(counter.swap(n), atomic.swap(n, Relaxed)),
+                    Op::CompareExchange(current, new) => {
+                        let current = current.unwrap_or_else(|| atomic.load(Relaxed));
+                        assert_eq!(
+                            counter.compare_exchange(current, new),
+                            atomic.compare_exchange(current, new, Relaxed, Relaxed),
+                        );
+                    }
+                    Op::FetchMax(n) => assert_eq!(counter.fetch_max(n), atomic.fetch_max(n, Relaxed)),
+                }
+                assert_eq!(counter.get(), atomic.load(Relaxed));
+            }
+        });
+    }
+
+    fn test_compare_exchange<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+
+        assert_eq!(counter.compare_exchange(0, 5), Err(u32::MAX as u64));
+        assert_eq!(counter.compare_exchange(u32::MAX as u64, 1 << 40), Ok(u32::MAX as u64));
+        assert_eq!(counter.get(), 1 << 40);
+
+        assert!(!counter.compare_and_incr(0));
+        assert!(counter.compare_and_incr(1 << 40));
+        assert_eq!(counter.get(), (1 << 40) + 1);
+    }
+
+    fn test_fetch_update_weak_panic_and_reentry<B: Backing>() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            counter.fetch_update_weak(|_| panic!("closure failed"))
+        }));
+        assert!(res.is_err());
+
+        // Neither locked up nor modified by the panic
+        assert_eq!(counter.get(), u32::MAX as u64);
+        assert_eq!(counter.try_get(), Ok(u32::MAX as u64));
+        assert_eq!(counter.incr(), u32::MAX as u64);
+        assert_eq!(counter.swap(7), 1 << 32);
+
+        // The closure may read the counter itself
+        assert_eq!(counter.fetch_update_weak(|num| Some(num + counter.get())), Ok(7));
+        assert_eq!(counter.get(), 14);
+    }
+
+    fn test_fetch_add_if_panicking_pred<B: Backing>() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let counter = unsafe { Counter64::<B>::with_init(u16::MAX as u64) };
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            counter.fetch_add_if(1, |num| if num > 10 { panic!("pred failed") } else { true })
+        }));
+        assert!(res.is_err());
+        assert_eq!(counter.get(), u16::MAX as u64);
+
+        // Still usable, by readers, writers and other conditional adds
+        assert_eq!(counter.fetch_add_if(1, |num| num % 2 == 1), Some(u16::MAX as u64));
+        assert_eq!(counter.fetch_add_if(1, |num| num % 2 == 1), None);
+        assert_eq!(counter.incr(), 1 << 16);
+        assert_eq!(counter.get(), (1 << 16) + 1);
+    }
+
+    fn test_multithread_fetch_update_weak_clamp<B: Backing>() {
+        const CAP: u64 = 30000;
+
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(u32::MAX as u64 - 10000) });
+        let cap = u32::MAX as u64 - 10000 + CAP;
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    (0..10000)
+                        .filter(|_| {
+                            counter
+                                .fetch_update_weak(|num| if num < cap { Some(num + 1) } else { None })
+                                .is_ok()
+                        })
+                        .count() as u64
+                })
+            })
+            .collect();
+
+        let accepted: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+        assert_eq!(accepted, CAP);
+        assert_eq!(counter.get(), cap);
+        assert_eq!(counter.fetch_update_weak(|num| Some(num.min(5))), Ok(cap));
+        assert_eq!(counter.get(), 5);
+    }
+
+    fn test_fetch_add_if_even<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64 - 1) };
+        // `is_multiple_of` needs Rust 1.87
+        #[allow(clippy::manual_is_multiple_of)]
+        let even = |num: u64| num % 2 == 0;
+
+        assert_eq!(counter.fetch_add_if(3, even), Some(u32::MAX as u64 - 1));
+        assert_eq!(counter.fetch_add_if(3, even), None);
+        assert_eq!(counter.get(), u32::MAX as u64 + 2);
+        assert_eq!(counter.fetch_add_if(3, even), None);
+        counter.incr();
+        assert_eq!(counter.fetch_add_if(0, even), Some(u32::MAX as u64 + 3));
+    }
+
+    fn test_multithread_fetch_add_if_below_cap<B: Backing>() {
+        let cap = u32::MAX as u64 + 1000;
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(u32::MAX as u64) });
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    let mut added = 0;
+                    while counter.fetch_add_if(3, |num| num + 3 <= cap).is_some() {
+                        added += 3;
+                    }
+                    added
+                })
+            })
+            .collect();
+
+        let added: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+        assert_eq!(added, 999);
+        assert_eq!(counter.get(), cap - 1);
+    }
+
+    fn test_multithread_compare_and_incr<B: Backing>() {
+        let counter = Arc::new(Counter64::<B>::new());
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    let mut won = vec![];
+
+                    loop {
+                        let seen = counter.get();
+                        if seen >= 10000 {
+                            return won;
+                        }
+                        if counter.compare_and_incr(seen) {
+                            won.push(seen);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut won: Vec<_> = handles.into_iter().flat_map(|h| h.join().unwrap()).collect();
+        won.sort();
+
+        assert_eq!(won, (0..10000).collect::<Vec<_>>());
+    }
+
+    fn test_try_errors<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+
+        assert_eq!(counter.try_get(), Ok(u32::MAX as u64));
+        assert_eq!(counter.try_get_u32(), Ok(u32::MAX));
+        assert_eq!(counter.try_add(1), Ok(u32::MAX as u64));
+        assert_eq!(counter.try_get_u32(), Err(CounterError::Truncation));
+        assert_eq!(counter.try_add(u64::MAX), Err(CounterError::Overflow));
+        assert_eq!(counter.get(), 1 << 32);
+
+        // Read in the middle of a write, only the multi-word backings have one
+        match counter.inner.with_write_held(|| counter.try_get()) {
+            Some(res) => assert_eq!(res, Err(CounterError::InconsistentRead)),
+            None => assert!(::std::mem::size_of::<B>() == 8, "{:?} has no write to hold", counter.inner),
+        }
+        assert_eq!(counter.try_get(), Ok(1 << 32));
+    }
+
+    fn test_will_wrap_near_overflow<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u64::MAX - 2) };
+
+        assert!(!counter.peek_will_wrap());
+        assert!(!counter.will_wrap_on_add(2));
+        assert!(counter.will_wrap_on_add(3));
+        assert!(!counter.will_wrap_on_add(0));
+
+        counter.incr();
+        assert!(!counter.peek_will_wrap());
+        counter.incr();
+        assert!(counter.peek_will_wrap());
+        assert!(!counter.will_wrap_on_add(0));
+
+        counter.wrapping_add(1);
+        assert_eq!(counter.get(), 0);
+        assert!(!counter.peek_will_wrap());
+        assert!(!counter.will_wrap_on_add(u64::MAX));
+    }
+
+    fn test_into_u64<B: Backing>() {
+        for &init in &[0, u32::MAX as u64 + 1, u64::MAX] {
+            let counter = unsafe { Counter64::<B>::with_init(init) };
+
+            assert_eq!(u64::from(&counter), init);
+
+            let num: u64 = (&counter).into();
+            assert_eq!(num, counter.get());
+        }
+    }
+
+    fn test_release_acquire_message_passing<B: Backing>() {
+        use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+        let counter = Arc::new(Counter64::<B>::new());
+        let slots = Arc::new((0..1000).map(|_| AtomicU64::new(0)).collect::<Vec<_>>());
+
+        let writer = {
+            let (counter, slots) = (counter.clone(), slots.clone());
+            thread::spawn(move|| {
+                for (i, slot) in slots.iter().enumerate() {
+                    slot.store(i as u64 * 3 + 1, Relaxed);
+                    assert_eq!(counter.incr_release(), i as u64);
+                }
+            })
+        };
+
+        let mut seen = 0;
+        while seen < 1000 {
+            seen = counter.get_acquire();
+            for (i, slot) in slots[..seen as usize].iter().enumerate() {
+                assert_eq!(slot.load(Relaxed), i as u64 * 3 + 1, "stale slot {}", i);
+            }
+        }
+
+        writer.join().unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    fn test_arc_shared_total<B: Backing>() {
+        let counters = [
+            Counter64::<B>::arc_new(),
+            unsafe { Counter64::<B>::with_init(u32::MAX as u64) }.into_arc(),
+        ];
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let counters = counters.clone();
+                thread::spawn(move|| {
+                    for _ in 0..1000 {
+                        counters[0].incr();
+                        counters[1].incr();
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(counters[0].get(), 4000);
+        assert_eq!(counters[1].get(), u32::MAX as u64 + 4000);
+    }
+
+    /// Stall an update midway while a reader and a writer pile up behind it
+    #[cfg(feature = "diagnostics")]
+    fn test_contention_stats<B: Backing>() {
+        use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+        use std::time::Duration;
+
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(u32::MAX as u64) });
+        let entered = Arc::new(AtomicBool::new(false));
+        let release = Arc::new(AtomicBool::new(false));
+        assert_eq!(counter.contention_stats(), ContentionStats::default());
+
+        let updater = {
+            let (counter, entered, release) = (counter.clone(), entered.clone(), release.clone());
+            thread::spawn(move|| {
+                counter.fetch_update(|num| {
+                    if !entered.swap(true, SeqCst) {
+                        while !release.load(SeqCst) {
+                            thread::yield_now();
+                        }
+                    }
+                    Some(num + 1)
+                })
+            })
+        };
+
+        while !entered.load(SeqCst) {
+            thread::yield_now();
+        }
+
+        let reader = {
+            let counter = counter.clone();
+            thread::spawn(move|| counter.get())
+        };
+        let writer = {
+            let counter = counter.clone();
+            thread::spawn(move|| counter.incr())
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        release.store(true, SeqCst);
+
+        assert!(updater.join().unwrap().is_ok());
+        reader.join().unwrap();
+        writer.join().unwrap();
+
+        // Single-word updaters lose the CAS to the writer,
+        // multi-word readers spin on the updater holding the lock.
+        let stats = counter.contention_stats();
+        assert!(stats.cas_retries + stats.read_retries > 0, "{:?}", stats);
+        assert_eq!(counter.get(), u32::MAX as u64 + 2);
+    }
+
+    #[cfg(feature = "rand")]
+    fn test_new_random_start<B: Backing>() {
+        let a = Counter64::<B>::new_random_start();
+        let b = Counter64::<B>::new_random_start();
+        let (start_a, start_b) = (a.get(), b.get());
+
+        // Equal with the probability of 2^-63
+        assert_ne!(start_a, start_b);
+        assert!(start_a < 1 << 63 && start_b < 1 << 63);
+
+        for i in 0..100 {
+            assert_eq!(a.incr(), start_a + i);
+            assert_eq!(b.add(3), start_b + i * 3);
+        }
+        assert_eq!(a.get(), start_a + 100);
+        assert_eq!(b.get(), start_b + 300);
+    }
+
+    #[cfg(feature = "test-util")]
+    fn test_assert_eq_value<B: Backing>() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+        counter.assert_eq_value(u32::MAX as u64);
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| counter.assert_eq_value(7)));
+        let message = *res.unwrap_err().downcast::<String>().unwrap();
+        assert_eq!(message, "counter value mismatch: expected 7, actual 4294967295");
+    }
+
+    fn test_fork_diverges<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+        let fork = counter.fork();
+        assert_eq!(fork.get(), u32::MAX as u64);
+
+        counter.add(10);
+        assert_eq!(fork.incr(), u32::MAX as u64);
+        fork.add(100);
+
+        assert_eq!(counter.get(), u32::MAX as u64 + 10);
+        assert_eq!(fork.get(), u32::MAX as u64 + 101);
+        assert_eq!(fork.fork().get(), fork.get());
+    }
+
+    fn test_publish_sequenced_handoff<B: Backing>() {
+        use std::sync::atomic::{AtomicU64, Ordering::{Relaxed, Release}};
+
+        const SLOTS: usize = 4;
+
+        // Producer cursor, consumer cursor and the ring between them
+        let shared = Arc::new((
+            Counter64::<B>::new(),
+            Counter64::<B>::new(),
+            (0..SLOTS).map(|_| AtomicU64::new(0)).collect::<Vec<_>>(),
+        ));
+
+        let producer = {
+            let shared = shared.clone();
+            thread::spawn(move|| {
+                let (produced, consumed, ring) = &*shared;
+
+                for i in 0..10000u64 {
+                    while i - consumed.get_acquire() >= SLOTS as u64 {
+                        thread::yield_now();
+                    }
+                    ring[i as usize % SLOTS].store(i * 7, Relaxed);
+                    assert_eq!(produced.publish(Release), i + 1);
+                }
+            })
+        };
+
+        let (produced, consumed, ring) = &*shared;
+        for i in 0..10000u64 {
+            while produced.get_acquire() <= i {
+                thread::yield_now();
+            }
+            assert_eq!(ring[i as usize % SLOTS].load(Relaxed), i * 7);
+            assert_eq!(consumed.publish(Release), i + 1);
+        }
+
+        producer.join().unwrap();
+    }
+
+    fn test_next_free_matches_get<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64 - 1) };
+
+        for _ in 0..3 {
+            let next = counter.incr_return_new();
+            assert_eq!(next, counter.get());
+        }
+
+        for _ in 0..3 {
+            let owned = counter.get();
+            let next = counter.incr_returning_next();
+            assert_eq!(next, owned + 1);
+            assert_eq!(next, counter.get());
+            assert_eq!(counter.try_reserve(2, u64::MAX), Some(next..next + 2));
+        }
+
+        let owned = counter.add(10);
+        let next = counter.add_return_new(5);
+        assert_eq!(next, owned + 15);
+        assert_eq!(next, counter.get());
+    }
+
+    fn test_observe_change<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+
+        let last = counter.get();
+        assert_eq!(counter.observe_change(last), None);
+
+        counter.incr();
+        assert_eq!(counter.observe_change(last), Some(1 << 32));
+        assert_eq!(counter.observe_change(1 << 32), None);
+        assert_eq!(counter.observe_change(0), Some(1 << 32));
+    }
+
+    fn test_spin_wait_change<B: Backing>() {
+        let counter = Arc::new(unsafe { Counter64::<B>::with_init(u32::MAX as u64) });
+        assert_eq!(counter.spin_wait_change(0), u32::MAX as u64);
+
+        let worker = {
+            let counter = counter.clone();
+            thread::spawn(move|| {
+                counter.incr();
+            })
+        };
+
+        assert_eq!(counter.spin_wait_change(u32::MAX as u64), 1 << 32);
+        worker.join().unwrap();
+    }
+
+    fn test_debug_in_derived_struct<B: Backing>() {
+        #[allow(dead_code)]
+        #[derive(Debug)]
+        struct Stats<B: Backing> {
+            hits: Counter64<B>,
+            misses: Counter64<B>,
+        }
+
+        let stats = Stats {
+            hits: unsafe { Counter64::<B>::with_init(u32::MAX as u64 + 1) },
+            misses: Counter64::<B>::new(),
+        };
+        stats.misses.add(3);
+
+        assert_eq!(
+            format!("{:?}", stats),
+            "Stats { hits: Counter64(4294967296), misses: Counter64(3) }",
+        );
+        assert_eq!(
+            format!("{:#?}", stats),
+            "Stats {\n    hits: Counter64(\n        4294967296,\n    ),\n    misses: Counter64(\n        3,\n    ),\n}",
+        );
+    }
+
+    fn test_bits<B: Backing>() {
+        let cases = [
+            (0, 0),
+            (1, 1),
+            (2, 2),
+            (255, 8),
+            (256, 9),
+            (u32::MAX as u64, 32),
+            (1 << 32, 33),
+            (u64::MAX, 64),
+        ];
+
+        for &(value, bits) in &cases {
+            let counter = unsafe { Counter64::<B>::with_init(value) };
+            assert_eq!(counter.bits(), bits, "bits of {}", value);
+        }
+    }
+
+    fn test_count_if<B: Backing>() {
+        let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64) };
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+        let mut trues = 0;
+
+        for _ in 0..1000 {
+            // `is_multiple_of` needs Rust 1.87
+            #[allow(clippy::manual_is_multiple_of)]
+            let cond = xorshift(&mut state) % 3 == 0;
+            trues += cond as u64;
+            assert_eq!(counter.count_if(cond), u32::MAX as u64 + trues);
+        }
+
+        assert!(0 < trues && trues < 1000);
+        assert_eq!(counter.get(), u32::MAX as u64 + trues);
+    }
+
+    fn test_signed_round_trip<B: Backing>() {
+        for &num in &[0, 1, -1, i32::MIN as i64, i32::MAX as i64 + 1, i64::MIN, i64::MAX] {
+            let counter = unsafe { Counter64::<B>::with_init_signed(num) };
+            assert_eq!(counter.as_signed(), num);
+            assert_eq!(counter.get(), num as u64);
+        }
+
+        let counter = unsafe { Counter64::<B>::with_init_signed(-2) };
+        counter.incr();
+        assert_eq!(counter.as_signed(), -1);
+        counter.wrapping_add(1);
+        assert_eq!(counter.as_signed(), 0);
+
+        let counter = unsafe { Counter64::<B>::with_init(i64::MAX as u64) };
+        counter.incr();
+        assert_eq!(counter.as_signed(), i64::MIN);
+    }
+
+    fn test_split_at_bit_round_trip<B: Backing>() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let num = 0xfedc_ba98_7654_3210;
+        let counter = unsafe { Counter64::<B>::with_init(num) };
+
+        assert_eq!(counter.split_at_bit(0), (num, 0));
+        assert_eq!(counter.split_at_bit(4), (0x0fed_cba9_8765_4321, 0x0));
+        assert_eq!(counter.split_at_bit(12), (0x000f_edcb_a987_6543, 0x210));
+        assert_eq!(counter.split_at_bit(32), (0xfedc_ba98, 0x7654_3210));
+        assert_eq!(counter.split_at_bit(63), (1, num & !(1 << 63)));
+        assert_eq!(counter.split_at_bit(64), (0, num));
+
+        for bit in 0..=64 {
+            let (high, low) = counter.split_at_bit(bit);
+            let rebuilt = unsafe { Counter64::<B>::from_parts(high, low, bit) };
+            assert_eq!(rebuilt.get(), num, "bit {}", bit);
+        }
+
+        let (high, low) = counter.split_at_bit(32);
+        assert_eq!(counter.split_half(), (high as u32, low as u32));
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| counter.split_at_bit(65))).is_err());
+        assert!(panic::catch_unwind(|| unsafe { Counter64::<B>::from_parts(0, 16, 4) }).is_err());
+        assert!(panic::catch_unwind(|| unsafe { Counter64::<B>::from_parts(16, 0, 60) }).is_err());
+    }
+
+    fn test_halves_round_trip<B: Backing>() {
+        let values = [0, 1, u32::MAX as u64, 1 << 32, 0x0123_4567_89AB_CDEF, u64::MAX];
+
+        for &value in &values {
+            let counter = unsafe { Counter64::<B>::with_init(value) };
+            let (high, low) = counter.split_half();
+            assert_eq!((high, low), ((value >> 32) as u32, value as u32));
+
+            let rebuilt = unsafe { Counter64::<B>::from_halves(high, low) };
+            assert_eq!(rebuilt.get(), value);
+        }
+
+        let counter = unsafe { Counter64::<B>::from_halves(1, u32::MAX) };
+        counter.incr();
+        assert_eq!(counter.split_half(), (2, 0));
+    }
+
+    #[test]
+    fn test_new_const_static() {
+        static COUNTER: Counter = new_const!(u32::MAX as u64 + 5);
+        const INIT: u64 = 1 << 40;
+        static OTHER: Counter = new_const!(INIT + 1);
+
+        assert_eq!(COUNTER.get(), u32::MAX as u64 + 5);
+        assert_eq!(COUNTER.incr(), u32::MAX as u64 + 5);
+        assert_eq!(COUNTER.get(), u32::MAX as u64 + 6);
+        assert_eq!(OTHER.get(), (1 << 40) + 1);
+    }
+
+    #[cfg(feature = "seqcst")]
+    #[test]
+    fn test_seqcst_message_passing() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        assert_eq!(backing::ORDER, Ordering::SeqCst);
+
+        let counter = Arc::new(Counter::new());
+        let data = Arc::new(AtomicU64::new(0));
+
+        let writer = {
+            let (counter, data) = (counter.clone(), data.clone());
+            thread::spawn(move|| {
+                for i in 1..=10000 {
+                    data.store(i, Ordering::Relaxed);
+                    counter.incr();
+                }
+            })
+        };
+
+        let mut seen = 0;
+        while seen < 10000 {
+            seen = counter.get();
+            assert!(data.load(Ordering::Relaxed) >= seen);
+        }
+
+        writer.join().unwrap();
+    }
 }
@@ -0,0 +1,157 @@
+//! Counter for a single thread, without atomics.
+
+use core::cell::Cell;
+use core::fmt;
+
+/// 64bit increase-only counter backed by a `Cell<u64>`
+///
+/// Offers the counting API of [`Counter`](type.Counter.html) with plain loads
+/// and stores instead of atomic operations, for single-threaded code like
+/// embedded main loops. In exchange it's `!Sync`, so it can't be shared
+/// across threads or put in a `static`:
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<counter64::LocalCounter>();
+/// ```
+///
+/// It's `Send`, so it can still be moved to another thread as a whole.
+/// Prefer `Counter` unless profiling shows the atomics matter.
+#[derive(Default)]
+pub struct LocalCounter(Cell<u64>);
+
+/// Formats as `LocalCounter(value)`, like `Counter64` does.
+impl fmt::Debug for LocalCounter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("LocalCounter").field(&self.get()).finish()
+    }
+}
+
+impl LocalCounter {
+    /// Create new counter from 0
+    pub const fn new() -> Self {
+        LocalCounter(Cell::new(0))
+    }
+
+    /// Create new counter from `num`
+    ///
+    /// # Safety
+    ///
+    /// Counter is expected to start from 0 and only increase.
+    /// Caller must ensure no one relies on it.
+    pub unsafe fn with_init(num: u64) -> Self {
+        LocalCounter(Cell::new(num))
+    }
+
+    /// Get counter's current value
+    #[inline]
+    pub fn get(&self) -> u64 {
+        self.0.get()
+    }
+
+    /// Increase counter by `n`, and return previous value
+    ///
+    /// Panics on overflow in debug builds like the integer arithmetic,
+    /// and wraps around in release builds.
+    #[inline]
+    pub fn add(&self, n: u64) -> u64 {
+        let prev = self.wrapping_add(n);
+        ::debug_check_overflow(prev, n);
+        prev
+    }
+
+    /// Increase counter by `n`, and return previous value
+    ///
+    /// Always wraps around on overflow, for modular arithmetic.
+    #[inline]
+    pub fn wrapping_add(&self, n: u64) -> u64 {
+        let prev = self.0.get();
+        self.0.set(prev.wrapping_add(n));
+        prev
+    }
+
+    /// Increase counter by 1, and return previous value
+    #[inline]
+    pub fn incr(&self) -> u64 {
+        self.add(1)
+    }
+
+    /// Increase counter by 1, and return new value
+    pub fn incr_return_new(&self) -> u64 {
+        self.add_return_new(1)
+    }
+
+    /// Increase counter by `n`, and return new value
+    pub fn add_return_new(&self, n: u64) -> u64 {
+        self.add(n).wrapping_add(n)
+    }
+
+    /// Increase counter by 1, and return previous value
+    ///
+    /// Returns `None` if it would overflow.
+    pub fn checked_incr(&self) -> Option<u64> {
+        self.checked_add(1)
+    }
+
+    /// Increase counter by `n`, and return previous value
+    ///
+    /// Returns `None` if it would overflow.
+    pub fn checked_add(&self, n: u64) -> Option<u64> {
+        let prev = self.0.get();
+        self.0.set(prev.checked_add(n)?);
+        Some(prev)
+    }
+
+    /// Consume the counter, and return its value
+    pub fn into_inner(self) -> u64 {
+        self.0.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Counter;
+
+    #[test]
+    fn test_parity_with_counter() {
+        let local = unsafe { LocalCounter::with_init(u32::MAX as u64 - 2) };
+        let counter = unsafe { Counter::with_init(u32::MAX as u64 - 2) };
+
+        for i in 0..100 {
+            assert_eq!(local.incr(), counter.incr());
+            assert_eq!(local.add(i), counter.add(i));
+            assert_eq!(local.incr_return_new(), counter.incr_return_new());
+            assert_eq!(local.get(), counter.get());
+        }
+
+        assert_eq!(format!("{:?}", local), format!("LocalCounter({})", counter.get()));
+        assert_eq!(local.into_inner(), counter.get());
+    }
+
+    #[test]
+    fn test_near_overflow() {
+        let local = unsafe { LocalCounter::with_init(u64::MAX - 1) };
+
+        assert_eq!(local.checked_incr(), Some(u64::MAX - 1));
+        assert_eq!(local.checked_incr(), None);
+        assert_eq!(local.get(), u64::MAX);
+        assert_eq!(local.wrapping_add(2), u64::MAX);
+        assert_eq!(local.get(), 1);
+    }
+
+    #[test]
+    fn test_send_across_threads() {
+        let local = LocalCounter::new();
+        local.add(5);
+
+        let local = std::thread::spawn(move|| {
+            local.incr();
+            local
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(local.get(), 6);
+    }
+}
@@ -0,0 +1,78 @@
+//! Static counters which start from a value computed at runtime.
+
+use std::sync::OnceLock;
+
+use Counter;
+
+/// Counter lazily initialized to a computed start value
+///
+/// For `static` counters whose start comes from e.g. a config file,
+/// which `const fn new` can't express.
+#[derive(Debug, Default)]
+pub struct OnceCounter {
+    cell: OnceLock<Counter>,
+}
+
+impl OnceCounter {
+    /// Create new uninitialized counter
+    pub const fn new() -> Self {
+        OnceCounter {
+            cell: OnceLock::new(),
+        }
+    }
+
+    /// Get the counter, initializing it to `init()` if it's not yet
+    ///
+    /// Among racing calls only one runs its `init`, the others block until
+    /// it returns and then see the counter it initialized, so the start value
+    /// is applied exactly once. Increments always happen after initialization.
+    /// If `init` panics the counter stays uninitialized.
+    pub fn get_or_init<F: FnOnce() -> u64>(&self, init: F) -> &Counter {
+        self.cell.get_or_init(|| unsafe { Counter::with_init(init()) })
+    }
+
+    /// Get the counter if it's initialized
+    pub fn counter(&self) -> Option<&Counter> {
+        self.cell.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::thread;
+
+    #[test]
+    fn test_racing_init_applies_once() {
+        static COUNTER: OnceCounter = OnceCounter::new();
+        static INITS: AtomicUsize = AtomicUsize::new(0);
+
+        assert!(COUNTER.counter().is_none());
+
+        let barrier = Barrier::new(8);
+        thread::scope(|s| {
+            for i in 0..8 {
+                let barrier = &barrier;
+                s.spawn(move|| {
+                    barrier.wait();
+                    let counter = COUNTER.get_or_init(|| {
+                        INITS.fetch_add(1, Ordering::Relaxed);
+                        1000 + i
+                    });
+
+                    for _ in 0..100 {
+                        counter.incr();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(INITS.load(Ordering::Relaxed), 1);
+
+        let start = COUNTER.counter().unwrap().get() - 800;
+        assert!((1000..1008).contains(&start), "start {}", start);
+        assert_eq!(COUNTER.get_or_init(|| unreachable!()).get(), start + 800);
+    }
+}
@@ -0,0 +1,57 @@
+//! Plain snapshots of counters for zero-copy serialization.
+
+use bytemuck::{Pod, Zeroable};
+
+use backing::Backing;
+use Counter64;
+
+/// Counter's value at some point, as a plain `u64` which is `bytemuck::Pod`
+///
+/// Counters themselves are atomics and can't be reinterpreted as bytes,
+/// but snapshots of them can, e.g. `bytemuck::cast_slice` an array of
+/// snapshots into a byte buffer for bulk serialization without copying.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct CounterPod(pub u64);
+
+// `#[repr(transparent)]` over `u64`, which is both.
+unsafe impl Zeroable for CounterPod {}
+unsafe impl Pod for CounterPod {}
+
+impl From<CounterPod> for u64 {
+    fn from(pod: CounterPod) -> u64 {
+        pod.0
+    }
+}
+
+impl<B: Backing> Counter64<B> {
+    /// Get counter's current value as a [`CounterPod`](struct.CounterPod.html)
+    pub fn to_pod(&self) -> CounterPod {
+        CounterPod(self.get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Counter;
+
+    #[test]
+    fn test_cast_slice_of_snapshots() {
+        let counters: [Counter; 3] = Default::default();
+        counters[1].add(0x0102_0304);
+        counters[2].add(u64::MAX);
+
+        let pods: Vec<CounterPod> = counters.iter().map(Counter::to_pod).collect();
+        let bytes: &[u8] = bytemuck::cast_slice(&pods);
+
+        assert_eq!(bytes.len(), 24);
+        assert_eq!(&bytes[8..16], &0x0102_0304u64.to_ne_bytes());
+        assert_eq!(&bytes[16..], &[0xff; 8]);
+
+        let back: &[CounterPod] = bytemuck::cast_slice(bytes);
+        assert_eq!(back, &pods[..]);
+        assert_eq!(u64::from(back[1]), 0x0102_0304);
+        assert_eq!(bytemuck::cast_slice::<CounterPod, u64>(&pods), &[0, 0x0102_0304, u64::MAX]);
+    }
+}
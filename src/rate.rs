@@ -0,0 +1,138 @@
+//! Smoothed increment rate of a counter.
+
+use std::time::Instant;
+
+use backing::Backing;
+use Counter64;
+
+/// Exponentially weighted moving average of a counter's increment rate
+///
+/// Sample the counter periodically with [`observe`](#method.observe).
+/// Each call folds the rate since the previous one into the average,
+/// weighting it by `alpha` and the average so far by `1 - alpha`.
+/// Rates are in increments per second.
+#[derive(Debug, Clone)]
+pub struct EwmaRate {
+    alpha: f64,
+    last: Option<(u64, Instant)>,
+    rate: Option<f64>,
+}
+
+impl EwmaRate {
+    /// Create new average weighting each new rate by `alpha`
+    ///
+    /// Larger `alpha` reacts faster, smaller one smooths more.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` is not in `(0, 1]`.
+    pub fn new(alpha: f64) -> Self {
+        assert!(alpha > 0.0 && alpha <= 1.0, "alpha must be in (0, 1], got {}", alpha);
+
+        EwmaRate {
+            alpha,
+            last: None,
+            rate: None,
+        }
+    }
+
+    /// Current average rate, `None` until two samples are observed
+    pub fn rate(&self) -> Option<f64> {
+        self.rate
+    }
+
+    /// Sample `counter` now, and return the updated average rate
+    pub fn observe<B: Backing>(&mut self, counter: &Counter64<B>) -> Option<f64> {
+        self.observe_at(counter.get(), Instant::now())
+    }
+
+    /// Fold the sample `value` taken at `now`, and return the updated average rate
+    ///
+    /// The first sample only sets the baseline and returns `None`,
+    /// the first rate seeds the average as is. A sample taken no later than
+    /// the previous one is ignored, its increments count toward the next interval.
+    pub fn observe_at(&mut self, value: u64, now: Instant) -> Option<f64> {
+        let (last_value, last_time) = match self.last {
+            Some(last) => last,
+            None => {
+                self.last = Some((value, now));
+                return None;
+            }
+        };
+
+        let elapsed = now.saturating_duration_since(last_time).as_secs_f64();
+        if elapsed == 0.0 {
+            return self.rate;
+        }
+
+        let current = value.wrapping_sub(last_value) as f64 / elapsed;
+        self.last = Some((value, now));
+        self.rate = Some(match self.rate {
+            Some(rate) => rate + self.alpha * (current - rate),
+            None => current,
+        });
+
+        self.rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use Counter;
+
+    #[test]
+    fn test_converges_to_steady_rate() {
+        let mut ewma = EwmaRate::new(0.3);
+        let start = Instant::now();
+        let interval = Duration::from_millis(100);
+
+        assert_eq!(ewma.observe_at(0, start), None);
+        assert_eq!(ewma.rate(), None);
+
+        // Seeded at 50/s, then a steady 200/s
+        assert_eq!(ewma.observe_at(5, start + interval), Some(50.0));
+
+        let mut prev_error = f64::INFINITY;
+        for i in 2..40 {
+            let rate = ewma.observe_at(5 + (i - 1) * 20, start + interval * i as u32).unwrap();
+            let error = (200.0 - rate).abs();
+            assert!(error < prev_error, "diverged at {}: {}", i, rate);
+            prev_error = error;
+        }
+
+        assert!(prev_error < 1e-3, "not converged: {}", prev_error);
+    }
+
+    #[test]
+    fn test_zero_elapsed_keeps_increments() {
+        let mut ewma = EwmaRate::new(1.0);
+        let start = Instant::now();
+
+        ewma.observe_at(0, start);
+        assert_eq!(ewma.observe_at(10, start), None);
+        assert_eq!(ewma.observe_at(20, start + Duration::from_secs(2)), Some(10.0));
+        assert_eq!(ewma.observe_at(30, start + Duration::from_secs(2)), Some(10.0));
+        assert_eq!(ewma.observe_at(30, start + Duration::from_secs(4)), Some(5.0));
+    }
+
+    #[test]
+    fn test_observe_counter() {
+        let counter = Counter::new();
+        let mut ewma = EwmaRate::new(0.5);
+
+        assert_eq!(ewma.observe(&counter), None);
+        counter.add(1000);
+        std::thread::sleep(Duration::from_millis(10));
+
+        let rate = ewma.observe(&counter).unwrap();
+        assert!(rate > 0.0 && rate <= 100_000.0, "rate: {}", rate);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_alpha() {
+        EwmaRate::new(0.0);
+    }
+}
@@ -0,0 +1,101 @@
+//! Collecting counters which come and go.
+
+use std::sync::{Arc, Mutex, Weak};
+
+use Counter;
+
+/// Set of counters to collect together, which doesn't keep them alive
+///
+/// It only holds `Weak` references, so a counter is dropped once its
+/// owners drop their `Arc`s, and [`snapshot`](#method.snapshot) skips it
+/// from then on. E.g. per-connection counters registered for the metrics
+/// endpoint don't leak after the connection closes.
+#[derive(Debug, Default)]
+pub struct Registry {
+    counters: Mutex<Vec<Weak<Counter>>>,
+}
+
+impl Registry {
+    /// Create new empty registry
+    pub const fn new() -> Self {
+        Registry {
+            counters: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add `counter` to the registry, without keeping it alive
+    pub fn register(&self, counter: &Arc<Counter>) {
+        self.counters.lock().unwrap().push(Arc::downgrade(counter));
+    }
+
+    /// Read every live counter's current value, in the order they're registered
+    ///
+    /// Dropped counters are skipped, and removed from the registry.
+    pub fn snapshot(&self) -> Vec<u64> {
+        let mut counters = self.counters.lock().unwrap();
+        let mut values = Vec::with_capacity(counters.len());
+
+        counters.retain(|weak| match weak.upgrade() {
+            Some(counter) => {
+                values.push(counter.get());
+                true
+            }
+            None => false,
+        });
+
+        values
+    }
+
+    /// Number of counters registered, including the dropped ones not yet removed
+    pub fn len(&self) -> usize {
+        self.counters.lock().unwrap().len()
+    }
+
+    /// Check whether no counter is registered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_skips_dropped() {
+        let registry = Registry::new();
+        assert_eq!(registry.snapshot(), Vec::<u64>::new());
+
+        let counters: Vec<Arc<Counter>> = (0..4)
+            .map(|i| Arc::new(unsafe { Counter::with_init(i * 10) }))
+            .collect();
+        for counter in &counters {
+            registry.register(counter);
+        }
+        counters[3].incr();
+        assert_eq!(registry.snapshot(), vec![0, 10, 20, 31]);
+
+        let mut counters = counters.into_iter();
+        let first = counters.next().unwrap();
+        let second = counters.next().unwrap();
+        let rest: Vec<_> = counters.collect();
+        drop(second);
+
+        // A clone still keeps the counter alive
+        let third = rest[0].clone();
+        drop(rest);
+
+        assert_eq!(registry.len(), 4);
+        assert_eq!(registry.snapshot(), vec![0, 20]);
+        assert_eq!(registry.len(), 2);
+
+        first.add(5);
+        drop(third);
+        assert_eq!(registry.snapshot(), vec![5]);
+        assert!(!registry.is_empty());
+
+        drop(first);
+        assert_eq!(registry.snapshot(), Vec::<u64>::new());
+        assert!(registry.is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+//! Counter whose resets are counted, so consumers can tell them apart from writes.
+
+use backing::{Backing, Native};
+use seqlock::SeqLock;
+use Counter64;
+
+/// Counter which can be reset, with the number of resets alongside its value
+///
+/// For counters whose displayed value legitimately restarts, e.g. per second,
+/// while consumers must not mistake the restart for the counter going backward.
+/// [`get_with_generation`](#method.get_with_generation) returns the reset count
+/// next to the value, so a reset is detected even to the current value,
+/// and no increment can pass for one.
+///
+/// Resets serialize on a sequence lock. Increments don't take it,
+/// so they cost the same as on the plain counter.
+#[derive(Debug)]
+pub struct ResettableCounter<B: Backing = Native> {
+    /// Only written by resets, so its sequence counts them
+    resets: SeqLock,
+    counter: Counter64<B>,
+}
+
+impl<B: Backing> Default for ResettableCounter<B> {
+    fn default() -> Self {
+        ResettableCounter::new()
+    }
+}
+
+impl<B: Backing> ResettableCounter<B> {
+    /// Create new counter from 0 with no resets
+    pub const fn new() -> Self {
+        ResettableCounter {
+            resets: SeqLock::new(),
+            counter: Counter64::new(),
+        }
+    }
+
+    /// Get counter's current value
+    pub fn get(&self) -> u64 {
+        self.counter.get()
+    }
+
+    /// Get the underlying counter
+    ///
+    /// Writes through it are ordinary writes, e.g. its `swap` is not counted as a reset.
+    pub fn counter(&self) -> &Counter64<B> {
+        &self.counter
+    }
+
+    /// Increase counter by 1, and return previous value
+    pub fn incr(&self) -> u64 {
+        self.counter.incr()
+    }
+
+    /// Increase counter by `n`, and return previous value
+    pub fn add(&self, n: u64) -> u64 {
+        self.counter.add(n)
+    }
+
+    /// Reset counter to `value`, and return previous value
+    ///
+    /// Like `swap` it can move the counter backward, but the reset count
+    /// advances along with it, even if the value stays the same.
+    /// Concurrent resets serialize with each other and with `get_with_generation`.
+    pub fn reset_to(&self, value: u64) -> u64 {
+        self.resets.write(|| self.counter.swap(value))
+    }
+
+    /// Get counter's current `(value, resets, generation)`
+    ///
+    /// `resets` counts the [`reset_to`](#method.reset_to) calls before the value was read,
+    /// so a consumer which sees it changed knows the value restarted.
+    /// `generation` is the counter's own, see
+    /// [`Counter64::get_with_generation`](struct.Counter64.html#method.get_with_generation).
+    pub fn get_with_generation(&self) -> (u64, u64, u64) {
+        let ((value, generation), seq) = self.resets.read_with_seq(|| self.counter.get_with_generation());
+
+        (value, (seq / 2) as u64, generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backing::{Lanes16, Lanes32};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn reset_mid_stream<B: Backing>() {
+        let counter = ResettableCounter::<B>::new();
+        counter.add(u32::MAX as u64 + 5);
+
+        let (before, resets, _) = counter.get_with_generation();
+        assert_eq!(resets, 0);
+        assert_eq!(counter.reset_to(10), before);
+        let (after, resets, _) = counter.get_with_generation();
+        assert_eq!((after, resets), (10, 1));
+
+        counter.incr();
+        assert_eq!(counter.get_with_generation().0, 11);
+
+        // Resetting to the current value is still a reset
+        assert_eq!(counter.reset_to(11), 11);
+        let (value, resets, generation) = counter.get_with_generation();
+        assert_eq!((value, resets), (11, 2));
+        assert_eq!(counter.get_with_generation(), (value, resets, generation));
+
+        // Neither is an increment of 2^32 one
+        counter.add(1 << 32);
+        assert_eq!(counter.get_with_generation().1, 2);
+        assert_eq!(counter.reset_to(11), 11 + (1 << 32));
+        assert_eq!(counter.get_with_generation().1, 3);
+    }
+
+    #[test]
+    fn test_reset_mid_stream() {
+        reset_mid_stream::<Native>();
+        reset_mid_stream::<Lanes32>();
+        reset_mid_stream::<Lanes16>();
+    }
+
+    #[test]
+    fn test_consumer_sees_resets() {
+        let counter = Arc::new(ResettableCounter::<Lanes16>::new());
+        let done = Arc::new(AtomicBool::new(false));
+
+        let consumer = {
+            let counter = counter.clone();
+            let done = done.clone();
+            thread::spawn(move|| {
+                let mut last = (0, 0);
+                while !done.load(Ordering::Relaxed) {
+                    let (value, resets, _) = counter.get_with_generation();
+                    // Within a reset the value only increases
+                    assert!(resets > last.1 || (resets == last.1 && value >= last.0));
+                    last = (value, resets);
+                }
+                last
+            })
+        };
+
+        for _ in 0..100 {
+            for _ in 0..100 {
+                counter.incr();
+            }
+            counter.reset_to(0);
+        }
+        done.store(true, Ordering::Relaxed);
+
+        let (_, seen) = consumer.join().unwrap();
+        assert!(seen <= 100);
+        let (value, resets, _) = counter.get_with_generation();
+        assert_eq!((value, resets), (0, 100));
+    }
+}
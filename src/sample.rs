@@ -0,0 +1,83 @@
+//! Counter values stamped with a monotonic time.
+
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use backing::Backing;
+use Counter64;
+
+/// Counter's value along with when it was read
+///
+/// `#[repr(C)]` with no padding, so its layout is stable for logging
+/// or shipping over the wire. It's `bytemuck::Pod` with the `bytemuck` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct Sample {
+    /// Counter's value
+    pub value: u64,
+    /// Nanoseconds since the first sample taken in this process, from a monotonic clock
+    ///
+    /// Only comparable between samples of the same process.
+    pub nanos: u64,
+}
+
+// `#[repr(C)]` of two `u64`s without padding.
+#[cfg(feature = "bytemuck")]
+unsafe impl ::bytemuck::Zeroable for Sample {}
+#[cfg(feature = "bytemuck")]
+unsafe impl ::bytemuck::Pod for Sample {}
+
+/// Monotonic origin of every `Sample::nanos`
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+impl<B: Backing> Counter64<B> {
+    /// Get counter's current value with a monotonic nanosecond timestamp
+    ///
+    /// Timestamp is taken after the read, so samples from the same thread
+    /// are ordered by both value and time.
+    pub fn sample_nanos(&self) -> Sample {
+        let epoch = epoch();
+        let value = self.get();
+        let nanos = epoch.elapsed().as_nanos();
+
+        Sample {
+            value,
+            nanos: nanos.min(u64::MAX as u128) as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Counter;
+
+    #[test]
+    fn test_samples_are_ordered() {
+        let counter = Counter::new();
+
+        let mut prev = counter.sample_nanos();
+        assert_eq!(prev.value, 0);
+
+        for _ in 0..1000 {
+            counter.add(3);
+            let sample = counter.sample_nanos();
+
+            assert_eq!(sample.value, prev.value + 3);
+            assert!(sample.nanos >= prev.nanos, "{:?} before {:?}", sample, prev);
+            prev = sample;
+        }
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_sample_is_pod() {
+        use super::Sample;
+
+        let sample = Sample { value: 7, nanos: 0x0102_0304 };
+        let words: &[u64] = ::bytemuck::cast_slice(::core::slice::from_ref(&sample));
+        assert_eq!(words, &[7, 0x0102_0304]);
+    }
+}
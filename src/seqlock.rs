@@ -0,0 +1,200 @@
+use core::hint::spin_loop;
+use core::sync::atomic::{fence, AtomicUsize, Ordering as O};
+
+/// Number of retries a reader spins before it starts backing off
+///
+/// Past this budget every retry yields the thread with the `std` feature,
+/// or spins progressively longer without it, so readers starved by
+/// writer-heavy load don't burn the CPU the writers need to finish.
+pub const READ_RETRY_LIMIT: u32 = 64;
+
+/// Wait before the next retry of a reader
+fn backoff(retries: u32) {
+    if retries < READ_RETRY_LIMIT {
+        spin_loop();
+        return;
+    }
+
+    #[cfg(feature = "std")]
+    ::std::thread::yield_now();
+
+    #[cfg(not(feature = "std"))]
+    {
+        let over = (retries - READ_RETRY_LIMIT).min(10);
+        for _ in 0..1u32 << over {
+            spin_loop();
+        }
+    }
+}
+
+/// Sequence lock which guards lanes of the multi-word counters.
+///
+/// Sequence number is odd while a writer is updating lanes.
+/// Lanes themselves are atomics so racy reads are not UB,
+/// they're just retried.
+#[derive(Debug)]
+pub struct SeqLock(AtomicUsize);
+
+impl SeqLock {
+    pub const fn new() -> Self {
+        SeqLock(AtomicUsize::new(0))
+    }
+
+    fn wait_even(&self) -> usize {
+        loop {
+            let seq = self.0.load(O::Acquire);
+
+            if seq & 1 == 0 {
+                return seq;
+            }
+
+            spin_loop();
+        }
+    }
+
+    /// Run `f` until it's not interleaved with any writer
+    ///
+    /// Backs off after `READ_RETRY_LIMIT` retries.
+    pub fn read<T, F: Fn() -> T>(&self, f: F) -> T {
+        self.read_counted(f).0
+    }
+
+    /// Run `f` like [`read`](#method.read), and also return the sequence
+    /// number it succeeded with, which advances by 2 per completed write
+    pub fn read_with_seq<T, F: Fn() -> T>(&self, f: F) -> (T, usize) {
+        let (res, seq, _) = self.read_counted(f);
+        (res, seq)
+    }
+
+    /// Run `f` like [`read`](#method.read), and also return how many times it retried
+    #[cfg(feature = "diagnostics")]
+    pub fn read_with_retries<T, F: Fn() -> T>(&self, f: F) -> (T, u32) {
+        let (res, _, retries) = self.read_counted(f);
+        (res, retries)
+    }
+
+    fn read_counted<T, F: Fn() -> T>(&self, f: F) -> (T, usize, u32) {
+        let mut retries = 0u32;
+
+        loop {
+            let seq = self.0.load(O::Acquire);
+
+            if seq & 1 == 0 {
+                let res = f();
+                fence(O::Acquire);
+
+                if self.0.load(O::Relaxed) == seq {
+                    return (res, seq, retries);
+                }
+            }
+
+            backoff(retries);
+            retries = retries.saturating_add(1);
+        }
+    }
+
+    /// Run `f` until it's not interleaved with any writer, retrying at most `max_retries` times
+    ///
+    /// If every attempt is interleaved, returns `Err` with one more unguarded run of `f`.
+    pub fn read_within<T, F: Fn() -> T>(&self, max_retries: u32, f: F) -> Result<T, T> {
+        for _ in 0..max_retries {
+            if let Some(res) = self.try_read(&f) {
+                return Ok(res);
+            }
+
+            spin_loop();
+        }
+
+        self.try_read(&f).ok_or_else(f)
+    }
+
+    /// Run `f` once, `None` if it's interleaved with any writer
+    pub fn try_read<T, F: FnOnce() -> T>(&self, f: F) -> Option<T> {
+        let seq = self.0.load(O::Acquire);
+
+        if seq & 1 != 0 {
+            return None;
+        }
+
+        let res = f();
+        fence(O::Acquire);
+
+        if self.0.load(O::Relaxed) == seq {
+            Some(res)
+        } else {
+            None
+        }
+    }
+
+    /// Run `f` exclusively against other writers
+    pub fn write<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let seq = loop {
+            let seq = self.wait_even();
+
+            if self.0.compare_exchange_weak(
+                seq, seq.wrapping_add(1), O::Acquire, O::Relaxed,
+            ).is_ok() {
+                break seq;
+            }
+        };
+
+        self.write_locked(seq, f)
+    }
+
+    /// Run `f` exclusively against other writers, if no write started since `seq`
+    ///
+    /// `seq` is the sequence number of a consistent read, e.g. from
+    /// [`read_with_seq`](#method.read_with_seq). Returns `None` without running `f`
+    /// if it's changed, so read-modify-write loops can run the modification
+    /// outside of the lock and only retry the store.
+    pub fn write_if_unchanged<T, F: FnOnce() -> T>(&self, seq: usize, f: F) -> Option<T> {
+        self.0
+            .compare_exchange(seq, seq.wrapping_add(1), O::Acquire, O::Relaxed)
+            .ok()
+            .map(|_| self.write_locked(seq, f))
+    }
+
+    /// Run `f` with the lock taken at `seq`, and release it even if `f` panics
+    fn write_locked<T, F: FnOnce() -> T>(&self, seq: usize, f: F) -> T {
+        let _guard = WriteGuard { lock: self, seq };
+        fence(O::Release);
+
+        f()
+    }
+}
+
+/// Releases the lock taken at `seq` on drop
+///
+/// Without it a panicking writer leaves the sequence odd,
+/// and every later reader and writer spins forever.
+struct WriteGuard<'a> {
+    lock: &'a SeqLock,
+    seq: usize,
+}
+
+impl<'a> Drop for WriteGuard<'a> {
+    fn drop(&mut self) {
+        self.lock.0.store(self.seq.wrapping_add(2), O::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn test_panicking_writer_releases() {
+        let lock = SeqLock::new();
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| lock.write(|| panic!("writer failed"))));
+        assert!(res.is_err());
+
+        assert_eq!(lock.try_read(|| 1), Some(1));
+        assert_eq!(lock.read_with_seq(|| 2), (2, 2));
+        assert_eq!(lock.write(|| 3), 3);
+        assert_eq!(lock.write_if_unchanged(2, || 4), None);
+        assert_eq!(lock.write_if_unchanged(4, || 5), Some(5));
+        assert_eq!(lock.read_with_seq(|| 6), (6, 6));
+    }
+}
@@ -0,0 +1,445 @@
+//! Reading or draining many counters at once.
+//!
+//! These aren't a single atomic operation across all the counters.
+//! Each value is individually consistent, but other threads may modify
+//! counters between reading one and the next.
+
+use core::array;
+use core::iter::Sum;
+
+use backing::Backing;
+use Counter64;
+
+/// Read every counter's current value, in order
+#[cfg(feature = "std")]
+pub fn snapshot_all<B: Backing>(counters: &[Counter64<B>]) -> Vec<u64> {
+    counters.iter().map(Counter64::get).collect()
+}
+
+/// Reset every counter to 0, and return their values before the reset, in order
+///
+/// For periodic metric collection. Each counter is reset with a single
+/// [`swap`](struct.Counter64.html#method.swap), so increments racing with it
+/// land either in this collection or the next one, never lost. Counters are
+/// reset one by one though, so the values aren't from a single instant.
+#[cfg(feature = "std")]
+pub fn observe_and_reset_all<B: Backing>(counters: &[Counter64<B>]) -> Vec<u64> {
+    counters.iter().map(|counter| counter.swap(0)).collect()
+}
+
+/// Read every counter's current value into an array, in order
+///
+/// Same as [`snapshot_all`](fn.snapshot_all.html) but without allocation,
+/// so it's available without `std`.
+///
+/// ```
+/// use counter64::{get_array, Counter, COUNTER_INIT};
+///
+/// static REQUESTS: [Counter; 3] = [COUNTER_INIT; 3];
+///
+/// REQUESTS[0].incr();
+/// REQUESTS[2].add(5);
+/// assert_eq!(get_array(&REQUESTS), [1, 0, 5]);
+/// ```
+pub fn get_array<B: Backing, const N: usize>(counters: &[Counter64<B>; N]) -> [u64; N] {
+    array::from_fn(|i| counters[i].get())
+}
+
+/// Attempts of [`snapshot_pair`](fn.snapshot_pair.html) before it settles
+const PAIR_RETRIES: usize = 8;
+
+/// Read two related counters as close to simultaneously as it can
+///
+/// Reads `a`, `b` then `a` again, and retries a few times if `a` moved in between.
+/// When `a` stays still the pair reflects a single instant of `a`, which reduces
+/// the skew of e.g. a hits/total ratio compared to reading them separately.
+///
+/// It's best-effort only. Two independent atomics can't be read atomically,
+/// so `b` may still move around the read, and under heavy load it gives up
+/// and returns the attempt where `a` moved the least.
+pub fn snapshot_pair<B: Backing>(a: &Counter64<B>, b: &Counter64<B>) -> (u64, u64) {
+    let mut best = (0, 0);
+    let mut best_drift = u64::MAX;
+
+    for _ in 0..PAIR_RETRIES {
+        let a1 = a.get();
+        let b1 = b.get();
+        let drift = a.get().wrapping_sub(a1);
+
+        if drift == 0 {
+            return (a1, b1);
+        }
+        if drift < best_drift {
+            best = (a1, b1);
+            best_drift = drift;
+        }
+    }
+
+    best
+}
+
+/// Ratio of two related counters, e.g. hits over total requests
+///
+/// Reads them with [`snapshot_pair`](fn.snapshot_pair.html), re-reading `den`
+/// around `num`, so the ratio is less skewed than from two separate reads.
+/// Returns `0.0` while `den` is 0, rather than `NaN` or infinity.
+///
+/// ```
+/// use counter64::{ratio, Counter};
+///
+/// let (hits, total) = (Counter::new(), Counter::new());
+/// assert_eq!(ratio(&hits, &total), 0.0);
+///
+/// total.add(4);
+/// hits.incr();
+/// assert_eq!(ratio(&hits, &total), 0.25);
+/// ```
+pub fn ratio<B: Backing>(num: &Counter64<B>, den: &Counter64<B>) -> f64 {
+    match snapshot_pair(den, num) {
+        (0, _) => 0.0,
+        (den, num) => num as f64 / den as f64,
+    }
+}
+
+/// Drain every counter into `dst`, and return the total drained
+///
+/// Each counter is reset to 0 with a single `swap`, like
+/// [`drain_into`](struct.Counter64.html#method.drain_into),
+/// so concurrent increments are either in the total or left in their counter
+/// for the next flush, never lost. Both `dst` and the total wrap around
+/// on overflow, so a drain never stops halfway.
+pub fn drain_all<B: Backing>(counters: &[Counter64<B>], dst: &Counter64<B>) -> u64 {
+    counters.iter().fold(0, |total, counter| {
+        let value = counter.swap(0);
+        dst.wrapping_add(value);
+        total.wrapping_add(value)
+    })
+}
+
+/// Sum every counter's current value, reading each once
+///
+/// It's a best-effort total. Each counter is read with `Relaxed` ordering
+/// at a slightly different instant, so under concurrent increments the result
+/// lies between the true totals at the start and at the end of the call,
+/// but may not be the total at any single instant. The bounds hold as long
+/// as the counters only increase during the call.
+/// It wraps around on overflow, like the counters themselves.
+pub fn relaxed_sum<B: Backing>(counters: &[&Counter64<B>]) -> u64 {
+    counters.iter().map(|counter| counter.get()).fold(0, u64::wrapping_add)
+}
+
+/// Attempts of [`consistent_sum`](fn.consistent_sum.html) before it gives up
+#[cfg(feature = "std")]
+const SUM_RETRIES: usize = 8;
+
+/// Sum every counter's value at a single instant, if it can find one
+///
+/// Reads every counter with its [generation](struct.Counter64.html#method.get_with_generation)
+/// twice, and returns the total once no generation moved between the two passes,
+/// i.e. no counter changed while reading them. Returns `None` if writers
+/// kept interfering for a few attempts. It's at least twice as expensive
+/// as [`relaxed_sum`](fn.relaxed_sum.html).
+///
+/// Single-word counters use the value as the generation, so a counter moved
+/// away and back between the passes, e.g. by `swap`, goes unnoticed.
+/// Like [`relaxed_sum`](fn.relaxed_sum.html), the total wraps around on overflow.
+#[cfg(feature = "std")]
+pub fn consistent_sum<B: Backing>(counters: &[&Counter64<B>]) -> Option<u64> {
+    let mut first: Vec<(u64, u64)> = counters.iter().map(|counter| counter.get_with_generation()).collect();
+
+    for _ in 0..SUM_RETRIES {
+        let second: Vec<(u64, u64)> = counters.iter().map(|counter| counter.get_with_generation()).collect();
+
+        if first == second {
+            return Some(second.iter().fold(0, |sum, &(value, _)| sum.wrapping_add(value)));
+        }
+
+        first = second;
+    }
+
+    None
+}
+
+/// Total of the counters' current values
+///
+/// Like [`snapshot_all`](fn.snapshot_all.html), each value is read individually,
+/// so the total may never have been the sum at any single instant.
+/// It wraps around on overflow, like the counters themselves.
+impl<'a, B: Backing> Sum<&'a Counter64<B>> for u64 {
+    fn sum<I: Iterator<Item = &'a Counter64<B>>>(iter: I) -> u64 {
+        iter.map(Counter64::get).fold(0, u64::wrapping_add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Counter;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_snapshot_all() {
+        let counters: Vec<Counter> = (0..8)
+            .map(|i| unsafe { Counter::with_init(i * 1000) })
+            .collect();
+        counters[3].incr();
+
+        assert_eq!(
+            snapshot_all(&counters),
+            vec![0, 1000, 2000, 3001, 4000, 5000, 6000, 7000],
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_observe_and_reset_all_conserves_total() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let counters: Arc<[Counter; 3]> = Arc::new(Default::default());
+
+        let producers: Vec<_> = (0..3)
+            .map(|i| {
+                let counters = counters.clone();
+                thread::spawn(move|| {
+                    for j in 0..30000 {
+                        counters[(i + j) % 3].add(i as u64 + 1);
+                    }
+                })
+            })
+            .collect();
+
+        let mut totals = [0u64; 3];
+        let mut collect = || {
+            for (total, value) in totals.iter_mut().zip(observe_and_reset_all(&counters[..])) {
+                *total += value;
+            }
+        };
+
+        while producers.iter().any(|p| !p.is_finished()) {
+            collect();
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        collect();
+
+        // Every producer spreads its adds evenly over the counters
+        assert_eq!(totals, [60000; 3]);
+        assert_eq!(observe_and_reset_all(&counters[..]), vec![0; 3]);
+    }
+
+    #[test]
+    fn test_get_array() {
+        let counters: [Counter; 4] = [
+            Counter::new(),
+            unsafe { Counter::with_init(1) },
+            unsafe { Counter::with_init(u32::MAX as u64 + 1) },
+            unsafe { Counter::with_init(u64::MAX) },
+        ];
+        counters[0].add(7);
+
+        assert_eq!(get_array(&counters), [7, 1, u32::MAX as u64 + 1, u64::MAX]);
+    }
+
+    #[test]
+    fn test_snapshot_pair_skew() {
+        use std::sync::Arc;
+        use std::thread;
+        use std::time::Duration;
+
+        let pair = Arc::new((Counter::new(), Counter::new()));
+
+        let writer = {
+            let pair = pair.clone();
+            thread::spawn(move|| {
+                for _ in 0..2000 {
+                    pair.0.incr();
+                    pair.1.incr();
+                    thread::sleep(Duration::from_micros(10));
+                }
+            })
+        };
+
+        while !writer.is_finished() {
+            let (total, hits) = snapshot_pair(&pair.0, &pair.1);
+            assert!(total.abs_diff(hits) <= 1, "skewed pair: {} / {}", hits, total);
+        }
+
+        writer.join().unwrap();
+        assert_eq!(snapshot_pair(&pair.0, &pair.1), (2000, 2000));
+    }
+
+    #[test]
+    fn test_ratio() {
+        let hits = Counter::new();
+        let total = Counter::new();
+        assert_eq!(ratio(&hits, &total), 0.0);
+
+        hits.add(5);
+        assert_eq!(ratio(&hits, &total), 0.0);
+
+        total.add(20);
+        assert_eq!(ratio(&hits, &total), 0.25);
+        hits.add(15);
+        assert_eq!(ratio(&hits, &total), 1.0);
+        assert_eq!(ratio(&total, &hits), 1.0);
+
+        let lanes: [::Counter64<::backing::Lanes16>; 2] = Default::default();
+        lanes[0].add(1 << 20);
+        lanes[1].add(1 << 22);
+        assert_eq!(ratio(&lanes[0], &lanes[1]), 0.25);
+        assert_eq!(ratio(&lanes[1], &lanes[0]), 4.0);
+    }
+
+    #[test]
+    fn test_ratio_bounded_under_writer() {
+        use std::sync::Arc;
+        use std::thread;
+        use std::time::Duration;
+
+        let pair = Arc::new((Counter::new(), Counter::new()));
+
+        let writer = {
+            let pair = pair.clone();
+            thread::spawn(move|| {
+                for i in 0..2000 {
+                    pair.1.incr();
+                    if i % 2 == 0 {
+                        pair.0.incr();
+                    }
+                    thread::sleep(Duration::from_micros(10));
+                }
+            })
+        };
+
+        while !writer.is_finished() {
+            let hit_rate = ratio(&pair.0, &pair.1);
+            assert!((0.0..=1.0).contains(&hit_rate), "hit rate {}", hit_rate);
+        }
+
+        writer.join().unwrap();
+        assert_eq!(ratio(&pair.0, &pair.1), 0.5);
+    }
+
+    #[test]
+    fn test_drain_all_conserves_total() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let shards: Arc<[Counter; 4]> = Arc::new(Default::default());
+        let total = Counter::new();
+
+        let producers: Vec<_> = (0..4)
+            .map(|i| {
+                let shards = shards.clone();
+                thread::spawn(move|| {
+                    for j in 0..20000 {
+                        shards[(i + j) % 4].incr();
+                    }
+                })
+            })
+            .collect();
+
+        let mut drained = 0;
+        while producers.iter().any(|p| !p.is_finished()) {
+            drained += drain_all(&shards[..], &total);
+        }
+
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        drained += drain_all(&shards[..], &total);
+
+        assert_eq!(drained, 80000);
+        assert_eq!(total.get(), 80000);
+        assert_eq!(get_array(&shards), [0; 4]);
+    }
+
+    #[test]
+    fn test_drain_all_wraps_around() {
+        let shards = [Counter::new(), Counter::new(), Counter::new()];
+        shards[0].add(u64::MAX);
+        shards[1].add(5);
+        shards[2].add(7);
+        let total = Counter::new();
+
+        assert_eq!(drain_all(&shards, &total), 11);
+        assert_eq!(get_array(&shards), [0; 3]);
+        assert_eq!(total.get(), 11);
+    }
+
+    #[test]
+    fn test_relaxed_sum_within_bounds() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let shards: Arc<[Counter; 4]> = Arc::new(Default::default());
+
+        let writers: Vec<_> = (0..2)
+            .map(|i| {
+                let shards = shards.clone();
+                thread::spawn(move|| {
+                    for j in 0..50000 {
+                        shards[(i + j) % 4].incr();
+                    }
+                })
+            })
+            .collect();
+
+        let refs: Vec<&Counter> = shards.iter().collect();
+        while writers.iter().any(|w| !w.is_finished()) {
+            let start = shards.iter().sum::<u64>();
+            let sum = relaxed_sum(&refs);
+            let end = shards.iter().sum::<u64>();
+            assert!(start <= sum && sum <= end, "{} not in {}..={}", sum, start, end);
+        }
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(relaxed_sum(&refs), 100000);
+        assert_eq!(relaxed_sum::<::backing::Native>(&[]), 0);
+
+        let max = Counter::new();
+        max.add(u64::MAX);
+        assert_eq!(relaxed_sum(&[&max, &shards[0], &max]), 25000 - 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_consistent_sum() {
+        let shards: [Counter; 3] = Default::default();
+        shards[1].add(10);
+        shards[2].add(u32::MAX as u64);
+
+        let refs: Vec<&Counter> = shards.iter().collect();
+        assert_eq!(consistent_sum(&refs), Some(10 + u32::MAX as u64));
+        assert_eq!(consistent_sum::<::backing::Native>(&[]), Some(0));
+
+        let lanes: [::Counter64<::backing::Lanes16>; 2] = Default::default();
+        lanes[0].add(3);
+        lanes[1].add(u16::MAX as u64 + 1);
+        let refs: Vec<_> = lanes.iter().collect();
+        assert_eq!(consistent_sum(&refs), Some(3 + u16::MAX as u64 + 1));
+
+        let max = Counter::new();
+        max.add(u64::MAX);
+        assert_eq!(consistent_sum(&[&max, &shards[1]]), Some(9));
+    }
+
+    #[test]
+    fn test_sum() {
+        let shards: [Counter; 4] = Default::default();
+
+        for (i, shard) in shards.iter().enumerate() {
+            shard.add(i as u64 * 10 + 1);
+        }
+
+        assert_eq!(shards.iter().sum::<u64>(), 1 + 11 + 21 + 31);
+        assert_eq!(shards[..0].iter().sum::<u64>(), 0);
+
+        shards[0].wrapping_add(u64::MAX - 1);
+        assert_eq!(shards.iter().sum::<u64>(), 11 + 21 + 31 - 1);
+    }
+}
@@ -0,0 +1,155 @@
+//! Counter paired with a generation, for ABA-safe version stamps.
+
+use seqlock::SeqLock;
+use Counter;
+
+/// Counter whose generation advances every time its value wraps around
+///
+/// Using a plain counter as a version stamp, e.g. in a free-list allocator,
+/// suffers the ABA problem once it wraps. The `(value, generation)` pair
+/// of this one never repeats until the generation wraps too.
+///
+/// Writers serialize on a sequence lock, so readers always see
+/// a value and generation which existed together.
+#[derive(Debug)]
+pub struct StampedCounter {
+    seq: SeqLock,
+    value: Counter,
+    generation: Counter,
+}
+
+impl Default for StampedCounter {
+    fn default() -> Self {
+        StampedCounter::new()
+    }
+}
+
+impl StampedCounter {
+    /// Create new stamped counter from 0 in generation 0
+    pub const fn new() -> Self {
+        StampedCounter {
+            seq: SeqLock::new(),
+            value: Counter::new(),
+            generation: Counter::new(),
+        }
+    }
+
+    /// Create new stamped counter from `value` in `generation`
+    ///
+    /// # Safety
+    ///
+    /// Counter is expected to start from 0 in generation 0 and only increase.
+    /// Caller must ensure no one relies on it.
+    pub unsafe fn with_init(value: u64, generation: u64) -> Self {
+        StampedCounter {
+            seq: SeqLock::new(),
+            value: Counter::with_init(value),
+            generation: Counter::with_init(generation),
+        }
+    }
+
+    /// Get counter's current value, regardless of the generation
+    pub fn get(&self) -> u64 {
+        self.get_stamped().0
+    }
+
+    /// Get counter's current `(value, generation)`
+    pub fn get_stamped(&self) -> (u64, u64) {
+        self.seq.read(|| (self.value.get(), self.generation.get()))
+    }
+
+    /// Get the total ever counted as `u128`, which doesn't wrap around
+    ///
+    /// The generation counts the wraps, so it's `generation << 64 | value`.
+    /// For long-term totals which may exceed `u64::MAX`.
+    pub fn total_u128(&self) -> u128 {
+        let (value, generation) = self.get_stamped();
+        (generation as u128) << 64 | value as u128
+    }
+
+    /// Increase counter by `n`, and return previous `(value, generation)`
+    ///
+    /// Wraps the value around on overflow, advancing the generation.
+    pub fn add(&self, n: u64) -> (u64, u64) {
+        self.seq.write(|| {
+            let value = self.value.wrapping_add(n);
+            let generation = if value.checked_add(n).is_none() {
+                self.generation.incr()
+            } else {
+                self.generation.get()
+            };
+
+            (value, generation)
+        })
+    }
+
+    /// Increase counter by 1, and return previous `(value, generation)`
+    pub fn incr(&self) -> (u64, u64) {
+        self.add(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_wrap_advances_generation() {
+        let counter = unsafe { StampedCounter::with_init(u64::MAX - 1, 0) };
+
+        assert_eq!(counter.incr(), (u64::MAX - 1, 0));
+        assert_eq!(counter.incr(), (u64::MAX, 0));
+        assert_eq!(counter.get_stamped(), (0, 1));
+        assert_eq!(counter.incr(), (0, 1));
+
+        assert_eq!(counter.add(u64::MAX), (1, 1));
+        assert_eq!(counter.get_stamped(), (0, 2));
+        assert_eq!(counter.add(0), (0, 2));
+        assert_eq!(counter.get(), 0);
+    }
+
+    #[test]
+    fn test_total_u128_across_wraps() {
+        let counter = unsafe { StampedCounter::with_init(u64::MAX - 2, 0) };
+        assert_eq!(counter.total_u128(), u64::MAX as u128 - 2);
+
+        let mut expected = u64::MAX as u128 - 2;
+        for &n in &[5, u64::MAX, 1, u64::MAX - 3, 100] {
+            counter.add(n);
+            expected += n as u128;
+            assert_eq!(counter.total_u128(), expected);
+        }
+
+        assert_eq!(counter.get_stamped().1, 3);
+        assert_eq!(counter.total_u128() >> 64, 3);
+    }
+
+    #[test]
+    fn test_multithread_stamps_never_repeat() {
+        let counter = Arc::new(unsafe { StampedCounter::with_init(u64::MAX - 10000, 7) });
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let counter = counter.clone();
+                thread::spawn(move|| {
+                    (0..5000).map(|_| counter.incr()).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut stamps: Vec<_> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .map(|(value, generation)| (generation, value))
+            .collect();
+        stamps.sort();
+        stamps.dedup();
+
+        assert_eq!(stamps.len(), 20000);
+        assert_eq!(stamps[0], (7, u64::MAX - 10000));
+        assert_eq!(stamps[19999], (8, 9998));
+        assert_eq!(counter.get_stamped(), (9999, 8));
+    }
+}
@@ -0,0 +1,85 @@
+//! Count, min and max of a value stream.
+
+use Counter;
+
+/// Number of recorded values along with the smallest and largest of them
+///
+/// E.g. for request sizes. Each of the three is updated atomically, but not
+/// together, so a [`snapshot`](#method.snapshot) racing with `record` may
+/// see a value counted before it's reflected in the min or max, or vice versa.
+#[derive(Debug)]
+pub struct Stats {
+    count: Counter,
+    min: Counter,
+    max: Counter,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats::new()
+    }
+}
+
+impl Stats {
+    /// Create new stats without any value recorded
+    pub const fn new() -> Self {
+        Stats {
+            count: Counter::new(),
+            // It only goes down from here, as a min should
+            min: unsafe { Counter::__with_init_const(u64::MAX) },
+            max: Counter::new(),
+        }
+    }
+
+    /// Record `value`, and return the number of values recorded before it
+    pub fn record(&self, value: u64) -> u64 {
+        let _ = self.min.fetch_update(|min| if value < min { Some(value) } else { None });
+        self.max.fetch_max(value);
+        self.count.incr()
+    }
+
+    /// Get `(count, min, max)` of the recorded values
+    ///
+    /// Before anything is recorded it's `(0, u64::MAX, 0)`.
+    pub fn snapshot(&self) -> (u64, u64, u64) {
+        (self.count.get(), self.min.get(), self.max.get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(Stats::new().snapshot(), (0, u64::MAX, 0));
+    }
+
+    #[test]
+    fn test_multithread_record() {
+        static STATS: Stats = Stats::new();
+
+        let handles: Vec<_> = (0..4u64)
+            .map(|i| {
+                thread::spawn(move|| {
+                    for j in 0..1000u64 {
+                        STATS.record(100 + (j * 7 + i * 13) % 5000);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let values = (0..4u64).flat_map(|i| (0..1000u64).map(move |j| 100 + (j * 7 + i * 13) % 5000));
+        let min = values.clone().min().unwrap();
+        let max = values.max().unwrap();
+
+        assert_eq!(STATS.snapshot(), (4000, min, max));
+        assert_eq!(STATS.record(0), 4000);
+        assert_eq!(STATS.snapshot(), (4001, 0, max));
+    }
+}
@@ -0,0 +1,101 @@
+//! IDs allocated in a strided sequence.
+
+use error::CounterError;
+use Counter;
+
+/// Counter handing out `start`, `start + step`, `start + 2 * step`, ...
+///
+/// E.g. node `n` of `k` nodes uses `start = n` and `step = k`, so the nodes
+/// generate IDs without colliding with each other and without coordination.
+#[derive(Debug)]
+pub struct SteppedCounter {
+    counter: Counter,
+    step: u64,
+}
+
+impl SteppedCounter {
+    /// Create new stepped counter claiming `start` first, then every `step` after it
+    ///
+    /// Returns `Err(CounterError::InvalidStep)` if `step` is 0.
+    pub fn with_start_and_step(start: u64, step: u64) -> Result<Self, CounterError> {
+        if step == 0 {
+            return Err(CounterError::InvalidStep);
+        }
+
+        Ok(SteppedCounter {
+            // The sequence starts here, it's not a jump of an existing counter.
+            counter: unsafe { Counter::with_init(start) },
+            step,
+        })
+    }
+
+    /// Claim the next value of the sequence, and return it
+    ///
+    /// Panics on overflow in debug builds, like [`Counter64::add`](struct.Counter64.html#method.add).
+    pub fn incr(&self) -> u64 {
+        self.counter.add(self.step)
+    }
+
+    /// Get the value the next [`incr`](#method.incr) will claim
+    pub fn get(&self) -> u64 {
+        self.counter.get()
+    }
+
+    /// Distance between the claimed values
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_sequence() {
+        let counter = SteppedCounter::with_start_and_step(3, 5).unwrap();
+
+        assert_eq!(counter.get(), 3);
+        assert_eq!((counter.incr(), counter.incr(), counter.incr()), (3, 8, 13));
+        assert_eq!((counter.get(), counter.step()), (18, 5));
+    }
+
+    #[test]
+    fn test_zero_step() {
+        assert_eq!(
+            SteppedCounter::with_start_and_step(0, 0).unwrap_err(),
+            CounterError::InvalidStep,
+        );
+    }
+
+    #[test]
+    fn test_multithread_disjoint_parity() {
+        let nodes = Arc::new([
+            SteppedCounter::with_start_and_step(0, 2).unwrap(),
+            SteppedCounter::with_start_and_step(1, 2).unwrap(),
+        ]);
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let nodes = nodes.clone();
+                thread::spawn(move|| {
+                    (0..1000).map(|_| nodes[i % 2].incr()).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut ids = HashSet::new();
+        for (i, handle) in handles.into_iter().enumerate() {
+            for id in handle.join().unwrap() {
+                assert_eq!(id % 2, i as u64 % 2);
+                assert!(ids.insert(id), "duplicated id {}", id);
+            }
+        }
+
+        assert_eq!(ids.len(), 4000);
+        assert_eq!(ids.iter().max(), Some(&3999));
+    }
+}
@@ -0,0 +1,82 @@
+//! Progress reporting on every multiple of a step.
+
+use backing::Backing;
+use error::CounterError;
+use Counter;
+use Counter64;
+
+/// Reports when a counter crosses a new multiple of `step`, e.g. every 1000 increments
+///
+/// Only milestones crossed after the notifier was created are reported.
+/// It can be shared, concurrent [`check`](#method.check)s report each
+/// milestone at most once.
+#[derive(Debug)]
+pub struct ThresholdNotifier<'a, B: Backing> {
+    counter: &'a Counter64<B>,
+    step: u64,
+    last: Counter,
+}
+
+impl<'a, B: Backing> ThresholdNotifier<'a, B> {
+    /// Create new notifier of `counter` reporting every multiple of `step`
+    ///
+    /// Returns `Err(CounterError::InvalidStep)` if `step` is 0.
+    pub fn new(counter: &'a Counter64<B>, step: u64) -> Result<Self, CounterError> {
+        if step == 0 {
+            return Err(CounterError::InvalidStep);
+        }
+
+        let milestone = counter.get() / step * step;
+
+        Ok(ThresholdNotifier {
+            counter,
+            step,
+            // It only tracks the milestones, which are increase-only.
+            last: unsafe { Counter::with_init(milestone) },
+        })
+    }
+
+    /// Get the latest milestone if it's crossed since the previous report
+    ///
+    /// When several are crossed between two checks, only the highest one is reported.
+    pub fn check(&self) -> Option<u64> {
+        let milestone = self.counter.get() / self.step * self.step;
+
+        self.last
+            .fetch_update(|last| if milestone > last { Some(milestone) } else { None })
+            .ok()
+            .map(|_| milestone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_in_bursts() {
+        let counter = Counter::new();
+        counter.add(250);
+
+        let notifier = ThresholdNotifier::new(&counter, 100).unwrap();
+        assert_eq!(notifier.check(), None);
+
+        counter.add(40);
+        assert_eq!(notifier.check(), None);
+        counter.add(10);
+        assert_eq!(notifier.check(), Some(300));
+        assert_eq!(notifier.check(), None);
+
+        counter.add(99);
+        assert_eq!(notifier.check(), None);
+        counter.add(351);
+        assert_eq!(notifier.check(), Some(700));
+        assert_eq!(notifier.check(), None);
+    }
+
+    #[test]
+    fn test_zero_step() {
+        let counter = Counter::new();
+        assert_eq!(ThresholdNotifier::new(&counter, 0).unwrap_err(), CounterError::InvalidStep);
+    }
+}
@@ -0,0 +1,105 @@
+//! Counting at most once per interval.
+
+use std::time::{Duration, Instant};
+
+use Counter;
+
+/// Counter which only increases if some interval has passed since its last increment
+///
+/// Useful for sampling, e.g. counting at most once per second.
+#[derive(Debug)]
+pub struct ThrottledCounter {
+    counter: Counter,
+    /// Nanoseconds since `epoch` plus 1 at the last increment, or 0 if never incremented
+    last_incr: Counter,
+    epoch: Instant,
+}
+
+impl Default for ThrottledCounter {
+    fn default() -> Self {
+        ThrottledCounter::new()
+    }
+}
+
+impl ThrottledCounter {
+    /// Create new throttled counter from 0
+    pub fn new() -> Self {
+        ThrottledCounter {
+            counter: Counter::new(),
+            last_incr: Counter::new(),
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Get counter's current value
+    pub fn get(&self) -> u64 {
+        self.counter.get()
+    }
+
+    /// Get the underlying counter
+    pub fn counter(&self) -> &Counter {
+        &self.counter
+    }
+
+    /// Increase counter by 1 if `interval` has passed since the last increment
+    ///
+    /// Returns whether it increased. The first call always does.
+    /// Among concurrent calls within the same interval only one succeeds.
+    pub fn incr_throttled(&self, interval: Duration) -> bool {
+        let now = self.epoch.elapsed().as_nanos() as u64 + 1;
+        let interval = interval.as_nanos() as u64;
+
+        let res = self.last_incr.fetch_update(|last| {
+            if last == 0 || now.saturating_sub(last) >= interval {
+                Some(now)
+            } else {
+                None
+            }
+        });
+
+        if res.is_ok() {
+            self.counter.incr();
+        }
+
+        res.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_incr_throttled() {
+        let interval = Duration::from_millis(50);
+        let counter = ThrottledCounter::new();
+        let start = Instant::now();
+
+        let mut accepted = 0;
+        while start.elapsed() < Duration::from_millis(275) {
+            if counter.incr_throttled(interval) {
+                accepted += 1;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        let elapsed = start.elapsed();
+
+        assert_eq!(counter.get(), accepted);
+        assert!(accepted >= 2, "accepted {} times", accepted);
+        assert!(
+            accepted as u128 <= elapsed.as_nanos() / interval.as_nanos() + 1,
+            "accepted {} times in {:?}", accepted, elapsed,
+        );
+    }
+
+    #[test]
+    fn test_first_incr_always_accepted() {
+        let counter = ThrottledCounter::new();
+
+        assert!(counter.incr_throttled(Duration::from_secs(3600)));
+        assert!(!counter.incr_throttled(Duration::from_secs(3600)));
+        assert!(counter.incr_throttled(Duration::from_secs(0)));
+        assert_eq!(counter.get(), 2);
+    }
+}
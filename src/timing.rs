@@ -0,0 +1,66 @@
+//! Counting scopes along with the time spent in them.
+
+use std::time::Instant;
+
+use backing::Backing;
+use Counter64;
+
+/// Guard which adds the nanoseconds since its creation to a counter on drop
+///
+/// Created by [`Counter64::scoped_increment`](struct.Counter64.html#method.scoped_increment).
+#[derive(Debug)]
+#[must_use = "the elapsed time is recorded when the guard is dropped"]
+pub struct TimingGuard<'a, B: Backing> {
+    time_counter: &'a Counter64<B>,
+    start: Instant,
+}
+
+impl<'a, B: Backing> Drop for TimingGuard<'a, B> {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed().as_nanos();
+        self.time_counter.saturating_add(elapsed.min(u64::MAX as u128) as u64);
+    }
+}
+
+impl<B: Backing> Counter64<B> {
+    /// Increase counter by 1, and add the nanoseconds until the returned guard
+    /// is dropped to `time_counter`
+    ///
+    /// For "calls and total time in this function" instrumentation.
+    /// Dividing the two gives the mean time per call.
+    pub fn scoped_increment<'a>(&'a self, time_counter: &'a Counter64<B>) -> TimingGuard<'a, B> {
+        self.incr();
+
+        TimingGuard {
+            time_counter,
+            start: Instant::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+    use Counter;
+
+    #[test]
+    fn test_scoped_increment_records_sleep() {
+        let calls = Counter::new();
+        let nanos = Counter::new();
+
+        {
+            let _guard = calls.scoped_increment(&nanos);
+            assert_eq!((calls.get(), nanos.get()), (1, 0));
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        assert_eq!(calls.get(), 1);
+        assert!(nanos.get() >= 20_000_000, "recorded {}ns", nanos.get());
+
+        let before = nanos.get();
+        drop(calls.scoped_increment(&nanos));
+        assert_eq!(calls.get(), 2);
+        assert!(nanos.get() >= before);
+    }
+}
@@ -0,0 +1,357 @@
+//! Future-based waiting on the counter's progress.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{fence, AtomicUsize, Ordering as O};
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+use backing::{Backing, Native};
+use Counter64;
+
+/// Wakers of the tasks waiting on a counter.
+///
+/// Writers only take the lock when there's some waker registered,
+/// so the hot path pays a fence and a load.
+#[derive(Debug)]
+struct Waiters {
+    len: AtomicUsize,
+    slots: Mutex<Slots>,
+}
+
+/// Registered wakers, keyed so each future can find and remove its own
+#[derive(Debug)]
+struct Slots {
+    next_key: u64,
+    wakers: Vec<(u64, Waker)>,
+}
+
+impl Waiters {
+    const fn new() -> Self {
+        Waiters {
+            len: AtomicUsize::new(0),
+            slots: Mutex::new(Slots {
+                next_key: 0,
+                wakers: Vec::new(),
+            }),
+        }
+    }
+
+    /// Register `waker` in the slot `key`, taking a new slot if it's gone
+    fn register(&self, key: &mut Option<u64>, waker: &Waker) {
+        let mut slots = self.slots.lock().unwrap();
+
+        match slots.wakers.iter_mut().find(|(k, _)| Some(*k) == *key) {
+            Some((_, registered)) => registered.clone_from(waker),
+            None => {
+                let new_key = slots.next_key;
+                slots.next_key += 1;
+                slots.wakers.push((new_key, waker.clone()));
+                self.len.store(slots.wakers.len(), O::Relaxed);
+                *key = Some(new_key);
+            }
+        }
+
+        // Pairs with the fence in `wake_all`,
+        // so either the poller sees the new value or the writer sees the waker.
+        fence(O::SeqCst);
+    }
+
+    /// Remove the slot `key` unless it's already woken
+    fn remove(&self, key: u64) {
+        let mut slots = self.slots.lock().unwrap();
+
+        if let Some(index) = slots.wakers.iter().position(|&(k, _)| k == key) {
+            slots.wakers.swap_remove(index);
+            self.len.store(slots.wakers.len(), O::Relaxed);
+        }
+    }
+
+    /// Wake every registered task, called after each write
+    fn wake_all(&self) {
+        fence(O::SeqCst);
+
+        if self.len.load(O::Relaxed) == 0 {
+            return;
+        }
+
+        let wakers = {
+            let mut slots = self.slots.lock().unwrap();
+            self.len.store(0, O::Relaxed);
+            ::std::mem::take(&mut slots.wakers)
+        };
+
+        for (_, waker) in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// Counter which tasks can wait on asynchronously
+///
+/// Wraps a [`Counter64`](struct.Counter64.html) with the list of waiting tasks,
+/// so only the counters which are actually awaited pay for it.
+/// Each write through this type pays a fence, and takes a lock if some task waits.
+/// The writes it doesn't mirror go through [`write`](#method.write).
+#[derive(Debug)]
+pub struct AsyncCounter<B: Backing = Native> {
+    counter: Counter64<B>,
+    waiters: Waiters,
+}
+
+impl<B: Backing> Default for AsyncCounter<B> {
+    fn default() -> Self {
+        AsyncCounter::new()
+    }
+}
+
+impl<B: Backing> AsyncCounter<B> {
+    /// Create new counter from 0
+    pub const fn new() -> Self {
+        AsyncCounter {
+            counter: Counter64::new(),
+            waiters: Waiters::new(),
+        }
+    }
+
+    /// Create new counter from `num`
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Counter64::with_init`](struct.Counter64.html#method.with_init).
+    pub unsafe fn with_init(num: u64) -> Self {
+        AsyncCounter {
+            counter: Counter64::with_init(num),
+            waiters: Waiters::new(),
+        }
+    }
+
+    /// Read counter's current value
+    pub fn get(&self) -> u64 {
+        self.counter.get()
+    }
+
+    /// Increase counter by 1, and return previous value
+    pub fn incr(&self) -> u64 {
+        self.add(1)
+    }
+
+    /// Increase counter by `n`, and return previous value
+    ///
+    /// Panics on overflow in debug builds, like [`Counter64::add`](struct.Counter64.html#method.add).
+    pub fn add(&self, n: u64) -> u64 {
+        self.write(|counter| counter.add(n))
+    }
+
+    /// Set counter to `max(current, value)`, and return previous value
+    ///
+    /// Wakes the waiting tasks only if it raised the counter.
+    pub fn fetch_max(&self, value: u64) -> u64 {
+        let prev = self.counter.fetch_max(value);
+
+        if value > prev {
+            self.waiters.wake_all();
+        }
+
+        prev
+    }
+
+    /// Run `f` on the underlying counter, and wake the waiting tasks afterwards
+    ///
+    /// For the writes this type doesn't mirror, e.g. `checked_add` or `swap`.
+    /// Tasks are woken even if `f` didn't change the counter, they check again.
+    pub fn write<T, F: FnOnce(&Counter64<B>) -> T>(&self, f: F) -> T {
+        let res = f(&self.counter);
+        self.waiters.wake_all();
+        res
+    }
+
+    /// Wait asynchronously until counter reaches `target`, and return its value
+    ///
+    /// The returned future is woken on each write to the counter,
+    /// and resolves once `get() >= target`. Dropping it while pending
+    /// unregisters its task.
+    pub fn wait_until_async(&self, target: u64) -> WaitUntil<'_, B> {
+        WaitUntil {
+            counter: self,
+            target,
+            key: None,
+        }
+    }
+
+    /// Unwrap the underlying counter
+    pub fn into_inner(self) -> Counter64<B> {
+        self.counter
+    }
+}
+
+/// Future returned by [`AsyncCounter::wait_until_async`](struct.AsyncCounter.html#method.wait_until_async)
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitUntil<'a, B: Backing = Native> {
+    counter: &'a AsyncCounter<B>,
+    target: u64,
+    /// Slot of the registered waker, if any
+    key: Option<u64>,
+}
+
+impl<'a, B: Backing> Future for WaitUntil<'a, B> {
+    type Output = u64;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u64> {
+        let value = self.counter.get();
+        if value >= self.target {
+            return Poll::Ready(value);
+        }
+
+        let this = &mut *self;
+        this.counter.waiters.register(&mut this.key, cx.waker());
+
+        // Check again, in case the write happened before the registration.
+        let value = self.counter.get();
+        if value >= self.target {
+            return Poll::Ready(value);
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<'a, B: Backing> Drop for WaitUntil<'a, B> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.counter.waiters.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::future;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use std::task::Wake;
+    use std::thread;
+    use std::time::Duration;
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, O::SeqCst);
+        }
+    }
+
+    fn counting_waker() -> (Arc<CountingWaker>, Waker) {
+        let wakes = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        (wakes.clone(), wakes.into())
+    }
+
+    fn registered(counter: &AsyncCounter) -> usize {
+        counter.waiters.slots.lock().unwrap().wakers.len()
+    }
+
+    #[test]
+    fn test_wait_until_async() {
+        let counter = Arc::new(AsyncCounter::<Native>::new());
+
+        let worker = {
+            let counter = counter.clone();
+            thread::spawn(move|| {
+                for _ in 0..1000 {
+                    counter.incr();
+                    thread::sleep(Duration::from_micros(10));
+                }
+            })
+        };
+
+        let value = block_on(counter.wait_until_async(500));
+        assert!(value >= 500);
+
+        worker.join().unwrap();
+        assert_eq!(block_on(counter.wait_until_async(1000)), 1000);
+        assert_eq!(registered(&counter), 0);
+    }
+
+    #[test]
+    fn test_wait_in_the_same_task() {
+        let counter = Arc::new(AsyncCounter::<Native>::new());
+
+        let worker = {
+            let counter = counter.clone();
+            thread::spawn(move|| {
+                for _ in 0..100 {
+                    counter.write(|counter| counter.wrapping_add(1));
+                    thread::sleep(Duration::from_micros(10));
+                }
+            })
+        };
+
+        let (first, second) = block_on(future::join(
+            counter.wait_until_async(50),
+            counter.wait_until_async(100),
+        ));
+        assert!(first >= 50);
+        assert_eq!(second, 100);
+
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn test_drop_unregisters() {
+        let counter = AsyncCounter::new();
+        let (wakes, waker) = counting_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        for _ in 0..10 {
+            let mut fut = Box::pin(counter.wait_until_async(100));
+            assert!(fut.as_mut().poll(&mut cx).is_pending());
+            assert_eq!(registered(&counter), 1);
+        }
+        assert_eq!(registered(&counter), 0);
+
+        // A future woken and dropped before it's polled again has nothing left to remove
+        let mut fut = Box::pin(counter.wait_until_async(100));
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        counter.incr();
+        assert_eq!(wakes.0.load(O::SeqCst), 1);
+        drop(fut);
+        assert_eq!(registered(&counter), 0);
+    }
+
+    #[test]
+    fn test_poll_again_keeps_one_slot() {
+        let counter = AsyncCounter::new();
+        let (_, first) = counting_waker();
+        let (wakes, second) = counting_waker();
+        let mut fut = Box::pin(counter.wait_until_async(100));
+
+        assert!(fut.as_mut().poll(&mut Context::from_waker(&first)).is_pending());
+        assert!(fut.as_mut().poll(&mut Context::from_waker(&second)).is_pending());
+        assert_eq!(registered(&counter), 1);
+
+        // Only the latest waker is woken
+        counter.incr();
+        assert_eq!(wakes.0.load(O::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_wakes_only_on_increase() {
+        let counter = unsafe { AsyncCounter::<Native>::with_init(10) };
+        let (wakes, waker) = counting_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = Box::pin(counter.wait_until_async(100));
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+
+        // Unchanged, nothing to wake for
+        counter.fetch_max(5);
+        assert_eq!(wakes.0.load(O::SeqCst), 0);
+
+        counter.fetch_max(20);
+        assert_eq!(wakes.0.load(O::SeqCst), 1);
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+    }
+}
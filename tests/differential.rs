@@ -0,0 +1,172 @@
+//! Replays the same random operations against every backing and a `Mutex<u64>` oracle.
+//!
+//! The seed and the number of operations can be picked with the environment, e.g.
+//! `COUNTER64_SEED=42 COUNTER64_OPS=1000000 cargo test --test differential`.
+//! Under Miri, which doesn't forward the environment, it replays a few short sequences.
+
+extern crate counter64;
+
+use std::env;
+use std::sync::Mutex;
+
+use counter64::backing::{self, Backing};
+use counter64::Counter64;
+
+const DEFAULT_SEEDS: u64 = if cfg!(miri) { 4 } else { 64 };
+const DEFAULT_OPS: usize = if cfg!(miri) { 100 } else { 2000 };
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Incr,
+    Add(u64),
+    Get,
+    Swap(u64),
+    CompareExchange(u64, u64),
+}
+
+/// Reference implementation, obviously correct
+struct Oracle(Mutex<u64>);
+
+impl Oracle {
+    fn apply(&self, op: Op) -> Result<u64, u64> {
+        let mut num = self.0.lock().unwrap();
+        let prev = *num;
+
+        match op {
+            Op::Incr => *num = prev.wrapping_add(1),
+            Op::Add(n) => *num = prev.wrapping_add(n),
+            Op::Get => {}
+            Op::Swap(value) => *num = value,
+            Op::CompareExchange(current, new) => {
+                if prev != current {
+                    return Err(prev);
+                }
+                *num = new;
+            }
+        }
+
+        Ok(prev)
+    }
+}
+
+fn apply<B: Backing>(counter: &Counter64<B>, op: Op) -> Result<u64, u64> {
+    match op {
+        Op::Incr => Ok(counter.wrapping_add(1)),
+        Op::Add(n) => Ok(counter.wrapping_add(n)),
+        Op::Get => Ok(counter.get()),
+        Op::Swap(value) => Ok(counter.swap(value)),
+        Op::CompareExchange(current, new) => counter.compare_exchange(current, new),
+    }
+}
+
+/// Xorshift64
+fn next(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Operand biased toward the lane and overflow boundaries
+fn operand(state: &mut u64) -> u64 {
+    const EDGES: [u64; 7] = [0, 1, 0xFFFF, u32::MAX as u64, 1 << 32, u64::MAX - 1, u64::MAX];
+
+    let r = next(state);
+    match r % 3 {
+        0 => EDGES[(r >> 8) as usize % EDGES.len()].wrapping_add(r >> 62),
+        1 => r >> 40,
+        _ => next(state),
+    }
+}
+
+fn ops(seed: u64, len: usize) -> Vec<Op> {
+    // Xorshift gets stuck at 0
+    let mut state = seed | 1 << 63;
+    let mut current = 0u64;
+
+    (0..len)
+        .map(|_| {
+            let op = match next(&mut state) % 7 {
+                0 => Op::Incr,
+                1 => Op::Add(operand(&mut state)),
+                2 => Op::Get,
+                3 => Op::Swap(operand(&mut state)),
+                // Mostly hit, occasionally miss
+                4 => Op::CompareExchange(operand(&mut state), operand(&mut state)),
+                _ => Op::CompareExchange(current, operand(&mut state)),
+            };
+            current = match op {
+                Op::Incr => current.wrapping_add(1),
+                Op::Add(n) => current.wrapping_add(n),
+                Op::Get => current,
+                Op::Swap(value) => value,
+                Op::CompareExchange(expected, new) if expected == current => new,
+                Op::CompareExchange(..) => current,
+            };
+            op
+        })
+        .collect()
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+fn check<B: Backing>() {
+    let seeds = match env::var("COUNTER64_SEED") {
+        Ok(seed) => {
+            let seed = seed.parse().expect("COUNTER64_SEED should be a u64");
+            seed..seed + 1
+        }
+        Err(_) => 0..DEFAULT_SEEDS,
+    };
+    let len = env_or("COUNTER64_OPS", DEFAULT_OPS);
+
+    for seed in seeds {
+        let oracle = Oracle(Mutex::new(0));
+        let counter = Counter64::<B>::new();
+
+        for (i, op) in ops(seed, len).into_iter().enumerate() {
+            assert_eq!(
+                apply(&counter, op), oracle.apply(op),
+                "seed {} op #{} {:?} on {}", seed, i, op, std::any::type_name::<B>(),
+            );
+        }
+
+        assert_eq!(counter.get(), *oracle.0.lock().unwrap(), "seed {}", seed);
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+#[test]
+fn test_atomic64() {
+    check::<backing::Atomic64>();
+}
+
+#[cfg(target_has_atomic = "32")]
+#[test]
+fn test_lanes32() {
+    check::<backing::Lanes32>();
+}
+
+#[test]
+fn test_lanes16() {
+    check::<backing::Lanes16>();
+}
+
+#[cfg(feature = "portable-atomic")]
+#[test]
+fn test_portable64() {
+    check::<backing::Portable64>();
+}
+
+#[cfg(feature = "spin")]
+#[test]
+fn test_locked64() {
+    check::<backing::Locked64>();
+}
+
+#[test]
+fn test_native() {
+    check::<backing::Native>();
+}
@@ -0,0 +1,87 @@
+//! Message passing through `incr_release` and `get_acquire`, for every backing.
+//!
+//! The message is a plain non-atomic write, so it's only data race free
+//! if the release increment happens-before the acquire read which observed it.
+//! On x86 it passes even with `Relaxed`, run it under Miri to check the orderings
+//! with `cargo +nightly miri test --test litmus`, which reports the data race
+//! if they're weakened.
+
+extern crate counter64;
+
+use std::cell::UnsafeCell;
+use std::hint::spin_loop;
+use std::thread;
+
+use counter64::backing::{self, Backing};
+use counter64::Counter64;
+
+const ROUNDS: u64 = if cfg!(miri) { 20 } else { 1000 };
+
+/// Slots written by the producer before publishing them with the counter
+struct Mailbox<B: Backing> {
+    published: Counter64<B>,
+    slots: Vec<UnsafeCell<u64>>,
+}
+
+// Each slot is written once before it's published, and only read after.
+unsafe impl<B: Backing> Sync for Mailbox<B> {}
+
+fn message_passing<B: Backing>() {
+    let mailbox = Mailbox {
+        published: Counter64::<B>::new(),
+        slots: (0..ROUNDS).map(|_| UnsafeCell::new(0)).collect(),
+    };
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            for (i, slot) in mailbox.slots.iter().enumerate() {
+                unsafe { *slot.get() = i as u64 * 3 + 1 };
+                assert_eq!(mailbox.published.incr_release(), i as u64);
+            }
+        });
+
+        let mut read = 0;
+        while read < ROUNDS {
+            let published = mailbox.published.get_acquire();
+            if published == read {
+                spin_loop();
+                continue;
+            }
+
+            for i in read..published {
+                let message = unsafe { *mailbox.slots[i as usize].get() };
+                assert_eq!(message, i * 3 + 1, "stale slot {}", i);
+            }
+            read = published;
+        }
+    });
+}
+
+#[cfg(target_has_atomic = "64")]
+#[test]
+fn test_atomic64() {
+    message_passing::<backing::Atomic64>();
+}
+
+#[cfg(target_has_atomic = "32")]
+#[test]
+fn test_lanes32() {
+    message_passing::<backing::Lanes32>();
+}
+
+#[test]
+fn test_lanes16() {
+    message_passing::<backing::Lanes16>();
+}
+
+#[cfg(feature = "portable-atomic")]
+#[test]
+fn test_portable64() {
+    message_passing::<backing::Portable64>();
+}
+
+#[cfg(feature = "spin")]
+#[test]
+fn test_locked64() {
+    message_passing::<backing::Locked64>();
+}
@@ -0,0 +1,102 @@
+//! Many threads incrementing each backing, to catch lost or doubled updates.
+//!
+//! Every increment's previous value is accounted for, so a lost update shows up
+//! in the final total and a doubled one in the sum of the returned values.
+//! A reader checks every value it sees is in range and never goes backward,
+//! so a torn read of the lanes shows up too.
+//! Scale it with the environment, e.g.
+//! `COUNTER64_STRESS_ITERS=10000000 COUNTER64_STRESS_REPS=10 cargo test --release --test stress`.
+//!
+//! It's small enough to run under Miri, which checks the lanes and their
+//! sequence lock for data races, with `cargo +nightly miri test --test stress`.
+//! Miri doesn't forward the environment, so it runs a few iterations per thread.
+
+extern crate counter64;
+
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use counter64::backing::{self, Backing};
+use counter64::Counter64;
+
+const THREADS: u64 = 8;
+const DEFAULT_ITERS: u64 = if cfg!(miri) { 20 } else { 100_000 };
+const DEFAULT_REPS: u64 = if cfg!(miri) { 1 } else { 3 };
+
+fn env_or(key: &str, default: u64) -> u64 {
+    env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+fn stress<B: Backing>() {
+    let iters = env_or("COUNTER64_STRESS_ITERS", DEFAULT_ITERS);
+    let reps = env_or("COUNTER64_STRESS_REPS", DEFAULT_REPS);
+    let total = THREADS * iters;
+
+    for rep in 0..reps {
+        // Start below the first lane boundary so carries are exercised
+        let start = u32::MAX as u64 - total / 2;
+        let counter = unsafe { Counter64::<B>::with_init(start) };
+
+        let end = start + total;
+        let done = AtomicBool::new(false);
+
+        let sum = thread::scope(|s| {
+            s.spawn(|| {
+                let mut prev = start;
+                while !done.load(Ordering::Relaxed) {
+                    let num = counter.get();
+                    assert!(prev <= num && num <= end, "{} after {} in rep {}", num, prev, rep);
+                    prev = num;
+                }
+            });
+
+            let handles: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    s.spawn(|| {
+                        (0..iters).fold(0u128, |sum, _| sum + counter.incr() as u128)
+                    })
+                })
+                .collect();
+
+            let sum = handles.into_iter().map(|h| h.join().unwrap()).sum::<u128>();
+            done.store(true, Ordering::Relaxed);
+            sum
+        });
+
+        assert_eq!(counter.get(), end, "lost updates in rep {}", rep);
+
+        // Sum of start..end, which only matches if each value was returned once
+        let expected = (start as u128 + end as u128 - 1) * total as u128 / 2;
+        assert_eq!(sum, expected, "duplicated previous values in rep {}", rep);
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+#[test]
+fn test_atomic64() {
+    stress::<backing::Atomic64>();
+}
+
+#[cfg(target_has_atomic = "32")]
+#[test]
+fn test_lanes32() {
+    stress::<backing::Lanes32>();
+}
+
+#[test]
+fn test_lanes16() {
+    stress::<backing::Lanes16>();
+}
+
+#[cfg(feature = "portable-atomic")]
+#[test]
+fn test_portable64() {
+    stress::<backing::Portable64>();
+}
+
+#[cfg(feature = "spin")]
+#[test]
+fn test_locked64() {
+    stress::<backing::Locked64>();
+}
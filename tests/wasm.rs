@@ -0,0 +1,79 @@
+//! Tests for `wasm32-unknown-unknown`, run in Node.js with `wasm-bindgen-test`:
+//! `cargo test --target wasm32-unknown-unknown --features single_thread --test wasm`
+//! with `wasm-bindgen-test-runner` configured as the runner.
+//!
+//! Without the `atomics` target feature wasm is single-threaded and its
+//! 64bit atomics compile to plain loads and stores. They still select `Atomic64`,
+//! and the lanes and the `!Sync` `LocalCounter` must behave the same on it.
+
+#![cfg(target_arch = "wasm32")]
+
+extern crate counter64;
+extern crate wasm_bindgen_test;
+
+use std::any::type_name;
+
+use counter64::backing::{self, Backing};
+use counter64::{Counter, Counter64};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn test_wasm_selects_atomic64() {
+    assert_eq!(type_name::<backing::Native>(), type_name::<backing::Atomic64>());
+}
+
+#[wasm_bindgen_test]
+fn test_incr_get_add() {
+    let counter = Counter::new();
+
+    assert_eq!(counter.incr(), 0);
+    assert_eq!(counter.add(41), 1);
+    assert_eq!(counter.get(), 42);
+}
+
+fn across_u32_boundary<B: Backing>() {
+    let counter = unsafe { Counter64::<B>::with_init(u32::MAX as u64 - 1) };
+
+    assert_eq!(counter.incr(), u32::MAX as u64 - 1);
+    assert_eq!(counter.incr(), u32::MAX as u64);
+    assert_eq!(counter.get(), u32::MAX as u64 + 1);
+    assert_eq!(counter.add(u32::MAX as u64), u32::MAX as u64 + 1);
+    assert_eq!(counter.get(), (u32::MAX as u64) * 2 + 1);
+    assert_eq!(counter.swap(5), (u32::MAX as u64) * 2 + 1);
+    assert_eq!(counter.compare_exchange(5, 1 << 40), Ok(5));
+    assert_eq!(counter.try_get(), Ok(1 << 40));
+}
+
+#[wasm_bindgen_test]
+fn test_across_u32_boundary() {
+    across_u32_boundary::<backing::Native>();
+}
+
+#[wasm_bindgen_test]
+fn test_lanes_across_u32_boundary() {
+    across_u32_boundary::<backing::Lanes32>();
+    across_u32_boundary::<backing::Lanes16>();
+}
+
+/// A single thread never interleaves with a writer, so reads never retry
+#[wasm_bindgen_test]
+fn test_single_thread_reads_are_exact() {
+    let counter = unsafe { Counter64::<backing::Lanes16>::with_init(u16::MAX as u64) };
+
+    for i in 0..1000 {
+        counter.incr();
+        assert_eq!(counter.get_approx(), u16::MAX as u64 + i + 1);
+        assert_eq!(counter.try_get_consistent_within(0), Ok(counter.get()));
+    }
+}
+
+#[cfg(feature = "single_thread")]
+#[wasm_bindgen_test]
+fn test_local_counter() {
+    use counter64::LocalCounter;
+
+    let counter = LocalCounter::new();
+    assert_eq!(counter.incr(), 0);
+    assert_eq!(counter.add(u32::MAX as u64), 1);
+    assert_eq!(counter.get(), u32::MAX as u64 + 1);
+}